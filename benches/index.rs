@@ -0,0 +1,138 @@
+//! Builds a synthetic vault and times polling and searching it, with and
+//! without an up to date index.
+//!
+//! Run with `cargo bench`, the size of the vault defaults to 50k notes and
+//! can be changed through `THOUGHTS_BENCH_NOTES`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use thoughts::database::Database;
+
+const WORDS_PER_NOTE: usize = 200;
+const VOCABULARY_SIZE: usize = 20_000;
+const SEARCH_RUNS: u32 = 10;
+
+/// Small xorshift generator, so the vault is the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, limit: usize) -> usize {
+        (self.next() % limit as u64) as usize
+    }
+}
+
+fn main() {
+    let note_count = env::var("THOUGHTS_BENCH_NOTES")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(50_000);
+
+    let root = env::temp_dir().join(format!("thoughts-bench-{}", std::process::id()));
+    let thoughts_path = root.join("vault");
+    let index_path = root.join("index.bin");
+
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let vocabulary = (0..VOCABULARY_SIZE)
+        .map(|_| {
+            let length = 3 + rng.below(8);
+            (0..length)
+                .map(|_| (b'a' + rng.below(26) as u8) as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>();
+
+    let started = Instant::now();
+    write_vault(&thoughts_path, note_count, &vocabulary, &mut rng);
+    report("write vault", started.elapsed());
+
+    let started = Instant::now();
+    let database = Database::new(thoughts_path.clone(), index_path.clone());
+    report("poll, building the index", started.elapsed());
+    drop(database);
+
+    let started = Instant::now();
+    let mut database = Database::new(thoughts_path.clone(), index_path.clone());
+    report("poll, index up to date", started.elapsed());
+
+    // Appending changes both the length and usually the modification time
+    for entry in database.entries.iter().take(100) {
+        let content = fs::read_to_string(&entry.path).unwrap();
+        fs::write(&entry.path, content + "\nchanged").unwrap();
+    }
+
+    let started = Instant::now();
+    let changes = database.poll().unwrap();
+    report(
+        &format!("poll, {} notes changed", changes.modified.len()),
+        started.elapsed(),
+    );
+
+    let started = Instant::now();
+    database.poll().unwrap();
+    report("poll, nothing changed", started.elapsed());
+
+    let phrase = format!("\"{} {}\"", vocabulary[0], vocabulary[1]);
+    let queries = [
+        vocabulary[42].clone(),
+        vocabulary[42][..3].to_string(),
+        format!("{} {}", vocabulary[7], vocabulary[8]),
+        phrase,
+        "changed".to_string(),
+    ];
+
+    for query in &queries {
+        let started = Instant::now();
+        let mut result_count = 0;
+
+        for _ in 0..SEARCH_RUNS {
+            result_count = database.search(query).unwrap().len();
+        }
+
+        report(
+            &format!("search `{}`, {} results", query, result_count),
+            started.elapsed() / SEARCH_RUNS,
+        );
+    }
+
+    fs::remove_file(&index_path).unwrap();
+
+    let started = Instant::now();
+    Database::new(thoughts_path, index_path);
+    report("poll, index deleted", started.elapsed());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+fn write_vault(thoughts_path: &Path, note_count: usize, vocabulary: &[String], rng: &mut Rng) {
+    fs::create_dir_all(thoughts_path).unwrap();
+
+    for note in 0..note_count {
+        let content = (0..WORDS_PER_NOTE)
+            .map(|word| {
+                let separator = match word % 12 {
+                    11 => ".\n",
+                    _ => " ",
+                };
+                // Skewed towards the start of the vocabulary, like real text
+                let limit = rng.below(VOCABULARY_SIZE) + 1;
+                let index = rng.below(limit);
+                format!("{}{}", vocabulary[index], separator)
+            })
+            .collect::<String>();
+
+        fs::write(thoughts_path.join(format!("note {}.md", note)), content).unwrap();
+    }
+}
+
+fn report(name: &str, duration: Duration) {
+    println!("{:<48} {:>10.2?}", name, duration);
+}
@@ -0,0 +1,417 @@
+use crate::config::Config;
+use crate::database::{Database, Entry, sanitize_title};
+use crate::editor;
+use crate::tags;
+use crate::templates::{Template, Variables};
+use anyhow::{Result, anyhow};
+use chrono::Local;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Titles taken from the text of a thought are cut off after this many characters
+const TITLE_LENGTH: usize = 60;
+
+/// Opens the user's editor on a temp file, and turns whatever was
+/// written into a new entry inside of the thoughts directory.
+///
+/// The temp file starts out empty, or as `template` with its variables filled in,
+/// for which the user is prompted on stdin if needed. A draft left behind by an
+/// earlier capture which failed is opened again instead, rather than overwritten.
+/// The title is taken from the first Markdown heading, if there is none
+/// the user is prompted for one on stdin.
+/// Returns `Ok(None)` if the buffer was left empty or as the template was,
+/// in which case nothing is written.
+///
+/// The temp file is only removed once the entry was written, so if anything
+/// goes wrong the draft is kept, and the error says where.
+pub fn new_thought(
+    config: &Config,
+    database: &Database,
+    template: Option<&Template>,
+) -> Result<Option<Entry>> {
+    let draft_path = &config.temp_file_path;
+    let mut title = None;
+    let mut seed = String::new();
+
+    let draft = fs::read_to_string(draft_path)
+        .ok()
+        .filter(|draft| !draft.trim().is_empty());
+
+    match (draft, template) {
+        (Some(draft), _) => fs::write(draft_path, draft)?,
+        (None, Some(template)) => {
+            // The title has to be known before the template can be filled in
+            if template.uses_title()? {
+                title = Some(prompt("title: ")?);
+            }
+
+            let now = Local::now();
+            let variables = Variables {
+                title: title.clone().unwrap_or_default(),
+                date: now.date_naive(),
+                time: now.time(),
+            };
+
+            seed = template.render(&variables, &mut |question| {
+                prompt(&format!("{}: ", question))
+            })?;
+
+            fs::write(draft_path, &seed)?;
+        }
+        (None, None) => fs::write(draft_path, "")?,
+    }
+
+    let kept =
+        |err: anyhow::Error| anyhow!("{}, the draft was kept at {}", err, draft_path.display());
+
+    editor::open(&config.editor_command, draft_path).map_err(kept)?;
+
+    let text = fs::read_to_string(draft_path).map_err(|err| kept(err.into()))?;
+
+    if text.trim().is_empty() || (!seed.is_empty() && text == seed) {
+        fs::remove_file(draft_path)?;
+        return Ok(None);
+    }
+
+    let entry = file_draft(database, &text, title, draft_path).map_err(kept)?;
+    fs::remove_file(draft_path)?;
+
+    Ok(Some(entry))
+}
+
+/// Writes the `text` of a draft as a new entry, see `new_thought`
+fn file_draft(
+    database: &Database,
+    text: &str,
+    title: Option<String>,
+    draft_path: &Path,
+) -> Result<Entry> {
+    // Frontmatter typed into the editor, or which came from the template, is kept
+    let entry = Entry::from_text(draft_path.to_path_buf(), text)?;
+
+    let title = match title {
+        Some(title) => title,
+        None => match first_heading(&entry.content) {
+            Some(heading) => heading.to_string(),
+            None => prompt("title: ")?,
+        },
+    };
+
+    create(database, &title, entry, &CaptureOptions::default())
+}
+
+/// How a thought written without an editor is filed, see `add_thought`
+#[derive(Debug, Default, Clone)]
+pub struct CaptureOptions {
+    /// Taken from the text of the thought when not given
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub favorite: bool,
+}
+
+/// Turns `text` into a new entry inside of the thoughts directory, without opening an editor.
+///
+/// Unless a title is given, the first Markdown heading is used as the title,
+/// or failing that the start of the first line.
+pub fn add_thought(database: &Database, text: &str, options: &CaptureOptions) -> Result<Entry> {
+    if text.trim().is_empty() {
+        return Err(anyhow!("thought is empty, nothing was written"));
+    }
+
+    let title = match &options.title {
+        Some(title) => title.clone(),
+        None => first_heading(text)
+            .or(text.lines().map(str::trim).find(|line| !line.is_empty()))
+            .unwrap_or_default()
+            .chars()
+            .take(TITLE_LENGTH)
+            .collect(),
+    };
+
+    let mut content = text.to_string();
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+
+    let entry = Entry {
+        content,
+        ..Default::default()
+    };
+
+    create(database, &title, entry, options)
+}
+
+/// Adds `text` as a timestamped list item at the end of the entry `name`, eg:
+/// `- 2026-10-17 09:30 call the plumber`. If there is no such entry, it's created.
+///
+/// Only that entry is read, so `database` doesn't need to have been polled.
+pub fn append_to_thought(
+    database: &mut Database,
+    name: &str,
+    text: &str,
+    options: &CaptureOptions,
+) -> Result<Entry> {
+    if text.trim().is_empty() {
+        return Err(anyhow!("thought is empty, nothing was written"));
+    }
+
+    // Later lines are indented, so that they stay part of the same list item
+    let line = format!(
+        "- {} {}\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        text.trim().replace('\n', "\n  ")
+    );
+
+    // Names which can't be used as a file stem are created sanitized, eg: `Todo: home`
+    // as `Todo- home.md`, so that's where a previous capture would have gone.
+    // Only a missing entry is created, not one which is ambiguous
+    let mut existing = database.load_entry(name)?.cloned();
+    if existing.is_none() {
+        existing = database.load_entry(&sanitize_title(name))?.cloned();
+    }
+
+    let mut entry = match existing {
+        Some(entry) => entry,
+        None => {
+            let entry = Entry {
+                content: line,
+                ..Default::default()
+            };
+
+            return create(database, name, entry, options);
+        }
+    };
+
+    if !entry.content.is_empty() && !entry.content.ends_with('\n') {
+        entry.content.push('\n');
+    }
+
+    entry.content.push_str(&line);
+    apply_options(&mut entry, options)?;
+    entry.set_modified(Local::now().timestamp() as u64);
+
+    database.write_entry(&entry)?;
+
+    Ok(entry)
+}
+
+/// Writes `entry` as a new entry named `title`
+fn create(
+    database: &Database,
+    title: &str,
+    mut entry: Entry,
+    options: &CaptureOptions,
+) -> Result<Entry> {
+    // The title may have been altered to make it a valid, unique file stem
+    entry.path = database.available_path(title);
+    entry.title = entry
+        .path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    apply_options(&mut entry, options)?;
+
+    let now = Local::now().timestamp() as u64;
+    entry.set_created(now);
+    entry.set_modified(now);
+
+    database.write_entry(&entry)?;
+
+    Ok(entry)
+}
+
+fn apply_options(entry: &mut Entry, options: &CaptureOptions) -> Result<()> {
+    for tag in &options.tags {
+        let tag = tags::normalize(tag)?;
+
+        if !tags::contains(&entry.tags, &tag) {
+            entry.tags.push(tag);
+        }
+    }
+
+    entry.favorite |= options.favorite;
+
+    Ok(())
+}
+
+/// Returns the text of the first ATX heading, eg: `## standup` yields `standup`
+fn first_heading(content: &str) -> Option<&str> {
+    content.lines().find_map(|line| {
+        let text = line.trim_start_matches('#');
+        let level = line.len() - text.len();
+
+        match (1..=6).contains(&level) && text.starts_with(' ') {
+            true => Some(text.trim()).filter(|text| !text.is_empty()),
+            false => None,
+        }
+    })
+}
+
+/// Asks for a line of input on stdin
+pub fn prompt(message: &str) -> Result<String> {
+    print!("{}", message);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(answer.trim().to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// A database in an empty directory, and a config whose editor replaces
+    /// the draft with whatever is in `typed.md` at the time
+    fn setup() -> (TempDir, Config, Database) {
+        let directory = TempDir::new().unwrap();
+        let editor = directory.path().join("editor.sh");
+
+        fs::write(
+            &editor,
+            format!(
+                "#!/bin/sh\ncat '{}' > \"$1\"\n",
+                directory.path().join("typed.md").display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = Config {
+            thoughts_path: directory.path().join("thoughts"),
+            temp_file_path: directory.path().join("thought.md"),
+            index_path: directory.path().join("index.bin"),
+            editor_command: editor.display().to_string(),
+            ..Default::default()
+        };
+
+        let database = Database::new(config.thoughts_path.clone(), config.index_path.clone());
+
+        (directory, config, database)
+    }
+
+    fn type_draft(directory: &TempDir, text: &str) {
+        fs::write(directory.path().join("typed.md"), text).unwrap();
+    }
+
+    #[test]
+    fn unparseable_drafts_are_kept() {
+        let (directory, config, database) = setup();
+        type_draft(&directory, "---\ntags: [work]\n# Standup\n");
+
+        let err = new_thought(&config, &database, None).unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains(&config.temp_file_path.display().to_string())
+        );
+        assert_eq!(
+            fs::read_to_string(&config.temp_file_path).unwrap(),
+            "---\ntags: [work]\n# Standup\n"
+        );
+        assert!(!config.thoughts_path.join("Standup.md").exists());
+    }
+
+    #[test]
+    fn drafts_are_removed_once_written() {
+        let (directory, config, database) = setup();
+        type_draft(&directory, "---\ntags: [work]\n---\n# Standup\n");
+
+        let entry = new_thought(&config, &database, None).unwrap().unwrap();
+
+        assert_eq!(entry.title, "Standup");
+        assert_eq!(entry.tags, ["work"]);
+        assert!(entry.path.exists());
+        assert!(!config.temp_file_path.exists());
+    }
+
+    #[test]
+    fn empty_drafts_write_nothing() {
+        let (directory, config, database) = setup();
+        type_draft(&directory, "\n");
+
+        assert!(new_thought(&config, &database, None).unwrap().is_none());
+        assert!(!config.temp_file_path.exists());
+        assert!(!config.thoughts_path.join("Untitled.md").exists());
+    }
+
+    /// A database which was only opened, the way `add` uses it
+    fn open(config: &Config) -> Database {
+        Database::open(config.thoughts_path.clone(), config.index_path.clone())
+    }
+
+    fn count_entries(config: &Config) -> usize {
+        let mut database = open(config);
+        database.poll().unwrap();
+        database.entries.len()
+    }
+
+    #[test]
+    fn appending_creates_the_entry_once() {
+        let (_directory, config, _) = setup();
+
+        for name in ["Todo: home", "work/inbox", "groceries"] {
+            let first =
+                append_to_thought(&mut open(&config), name, "one", &Default::default()).unwrap();
+            let second =
+                append_to_thought(&mut open(&config), name, "two", &Default::default()).unwrap();
+
+            assert_eq!(first.path, second.path, "{}", name);
+
+            let lines = second.content.lines().collect::<Vec<&str>>();
+            assert_eq!(lines.len(), 2);
+            assert!(lines[0].starts_with("- ") && lines[0].ends_with(" one"));
+            assert!(lines[1].starts_with("- ") && lines[1].ends_with(" two"));
+        }
+
+        assert_eq!(count_entries(&config), 3);
+    }
+
+    #[test]
+    fn appending_keeps_what_was_there() {
+        let (_directory, config, _) = setup();
+        let path = config.thoughts_path.join("notes/inbox.md");
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "---\nfavorite: true\n---\n# Inbox\nno newline").unwrap();
+
+        let options = CaptureOptions {
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+
+        for name in ["inbox", "notes/inbox", "notes/inbox.md"] {
+            append_to_thought(&mut open(&config), name, "call\nback", &options).unwrap();
+        }
+
+        let entry = Entry::from_text(path.clone(), &fs::read_to_string(&path).unwrap()).unwrap();
+        let lines = entry.content.lines().collect::<Vec<&str>>();
+
+        assert!(entry.favorite);
+        assert_eq!(entry.tags, ["work"]);
+        assert_eq!(lines[..2], ["# Inbox", "no newline"]);
+        assert_eq!(lines.len(), 8);
+        assert!(lines[2].ends_with(" call") && lines[3] == "  back");
+        assert_eq!(count_entries(&config), 1);
+    }
+
+    #[test]
+    fn appending_to_an_ambiguous_title_fails() {
+        let (_directory, config, _) = setup();
+
+        for directory in ["a", "b"] {
+            let path = config.thoughts_path.join(directory).join("inbox.md");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        assert!(append_to_thought(&mut open(&config), "inbox", "x", &Default::default()).is_err());
+        assert_eq!(count_entries(&config), 2);
+    }
+}
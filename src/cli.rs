@@ -0,0 +1,161 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use thoughts::export::Format;
+use thoughts::search::SortOrder;
+
+/// A place to put your thoughts.
+/// Launches the TUI when no command is given.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List thoughts, most recently modified first unless configured otherwise
+    List {
+        #[command(flatten)]
+        filter: Filter,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Print the content of a thought
+    Show {
+        /// Title of the thought, or its path within the thoughts directory
+        name: String,
+    },
+    /// Write a new thought in your editor
+    New {
+        /// Start out as this template from the `templates` directory, eg: `meeting`
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Write a thought straight from the command line, without opening an editor
+    Add {
+        /// Text of the thought, or `-` to read it from stdin
+        text: String,
+        /// Title of the thought, taken from the start of the text by default
+        #[arg(long, conflicts_with = "append_to")]
+        title: Option<String>,
+        /// Tag the thought. Can be given more than once
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Mark the thought as a favorite
+        #[arg(long)]
+        favorite: bool,
+        /// Add the text as a timestamped line at the end of this thought,
+        /// which is created if it doesn't exist yet
+        #[arg(long, value_name = "NAME")]
+        append_to: Option<String>,
+    },
+    /// Open a thought in your editor
+    Edit {
+        /// Title of the thought, or its path within the thoughts directory
+        name: String,
+    },
+    /// List thoughts matching a query, best matches first
+    Search {
+        /// Same syntax as the search bar, eg: `standup tag:work modified:last-7d`
+        query: String,
+        #[command(flatten)]
+        filter: Filter,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Add tags to a thought, or remove them when prefixed with `-`
+    Tag {
+        /// Title of the thought, or its path within the thoughts directory
+        name: String,
+        /// Tags to add, eg: `work -draft` adds `work` and removes `draft`
+        #[arg(required = true, allow_hyphen_values = true)]
+        tags: Vec<String>,
+    },
+    /// Rename a tag on every thought, along with the tags nested under it.
+    /// Thoughts which already have the new tag simply lose the old one
+    RenameTag {
+        /// Tag to rename, eg: `project`, which also renames `project/alpha`
+        from: String,
+        /// New name of the tag
+        to: String,
+        /// Only rename the tag on this thought, given by its title or its path
+        /// within the thoughts directory
+        #[arg(long, value_name = "NAME")]
+        only: Option<String>,
+    },
+    /// Mark a thought as a favorite, or unmark it if it already is one
+    Fav {
+        /// Title of the thought, or its path within the thoughts directory
+        name: String,
+    },
+    /// Move a thought to the trash
+    Rm {
+        /// Title of the thought, or its path within the thoughts directory
+        name: String,
+    },
+    /// Print counts of thoughts, favorites, tags and words
+    Stats,
+    /// Open today's daily note in your editor, creating it if needed
+    Today,
+    /// Open yesterday's daily note in your editor, creating it if needed
+    Yesterday,
+    /// Open the daily note of any day in your editor, creating it if needed
+    Date {
+        /// Eg: `2026-10-17`
+        date: NaiveDate,
+    },
+    /// Store when every thought was created and last modified in its frontmatter,
+    /// so that the times survive sync tools and checkouts
+    Migrate,
+    /// Manage deleted thoughts
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommand {
+    /// List deleted thoughts, most recently deleted first
+    List,
+    /// Move a deleted thought back to where it was
+    Restore {
+        /// Id of the thought as shown by `trash list`, or its title if that's unambiguous
+        id: String,
+    },
+    /// Delete thoughts in the trash for good
+    Purge {
+        /// Id of the thought as shown by `trash list`, or its title if that's unambiguous
+        #[arg(required_unless_present = "all")]
+        id: Option<String>,
+        /// Empty the whole trash
+        #[arg(long, conflicts_with = "id")]
+        all: bool,
+    },
+}
+
+/// Narrows down and orders the thoughts listed by `list` and `search`
+#[derive(Args)]
+pub struct Filter {
+    /// Only list thoughts with this tag, or a tag nested under it. Can be given more than once
+    #[arg(long)]
+    pub tag: Vec<String>,
+    /// Only list favorites
+    #[arg(long)]
+    pub favorites: bool,
+    /// Order to list thoughts in
+    #[arg(long, value_enum)]
+    pub sort: Option<SortOrder>,
+}
+
+/// How `list` and `search` print thoughts
+#[derive(Args)]
+pub struct Output {
+    /// Print thoughts as plain text, or in a format meant for other programs
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+    /// Include the content of each thought, for every format but plain text
+    #[arg(long)]
+    pub content: bool,
+}
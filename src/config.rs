@@ -1,4 +1,6 @@
+use crate::search::SortOrder;
 use anyhow::{Result, anyhow};
+use chrono::format::{Item, StrftimeItems};
 use figment::Figment;
 use figment::providers::{Env, Format, Serialized, Toml};
 use serde::{Deserialize, Serialize};
@@ -9,8 +11,20 @@ use std::path::PathBuf;
 struct ConfigProto {
     pub thoughts_path: Option<PathBuf>,
     pub temp_file_path: Option<PathBuf>,
+    pub index_path: Option<PathBuf>,
     pub editor_command: Option<String>,
     pub reactive: Option<bool>,
+    pub watch: Option<bool>,
+    pub pin_favorites: Option<bool>,
+    pub trash_retention_days: Option<u64>,
+    pub date_format: Option<String>,
+    pub clock_24h: Option<bool>,
+    pub relative_time: Option<bool>,
+    pub list_timestamp: Option<ListTimestamp>,
+    pub sort_order: Option<SortOrder>,
+    pub daily_directory: Option<PathBuf>,
+    pub daily_filename_format: Option<String>,
+    pub daily_template: Option<PathBuf>,
     pub min_width: Option<u16>,
     pub min_height: Option<u16>,
     pub react_width: Option<u16>,
@@ -26,11 +40,31 @@ impl Default for ConfigProto {
             None
         };
 
+        let index_path = if cfg!(unix) {
+            Some(PathBuf::from("~/.cache/thoughts/index.bin"))
+        } else if cfg!(windows) {
+            Some(PathBuf::from("%LOCALAPPDATA%\\Thoughts\\index.bin"))
+        } else {
+            None
+        };
+
         ConfigProto {
             thoughts_path: None,
             temp_file_path,
+            index_path,
             editor_command: None,
             reactive: Some(true),
+            watch: Some(true),
+            pin_favorites: Some(false),
+            trash_retention_days: Some(30),
+            date_format: Some(String::from("%m-%d-%Y")),
+            clock_24h: Some(false),
+            relative_time: Some(false),
+            list_timestamp: Some(ListTimestamp::Modified),
+            sort_order: Some(SortOrder::Relevance),
+            daily_directory: Some(PathBuf::from("daily")),
+            daily_filename_format: Some(String::from("%Y-%m-%d")),
+            daily_template: None,
             min_width: Some(58),
             min_height: Some(18),
             react_width: Some(80),
@@ -38,12 +72,48 @@ impl Default for ConfigProto {
     }
 }
 
-#[derive(Debug, Default)]
+/// Which of the times of an entry to show
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListTimestamp {
+    Created,
+    #[default]
+    Modified,
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct Config {
     pub thoughts_path: PathBuf,
     pub temp_file_path: PathBuf,
+    /// Where the full-text index is cached
+    pub index_path: PathBuf,
     pub editor_command: String,
     pub reactive: bool,
+    /// Whether to watch the thoughts directory for changes, rather than
+    /// checking on a timer. Watching doesn't work on some network filesystems
+    pub watch: bool,
+    /// Whether favorites are always listed before other entries
+    pub pin_favorites: bool,
+    /// How long deleted entries are kept in the trash, 0 keeps them forever
+    pub trash_retention_days: u64,
+    /// strftime-style format of the dates in the note list, eg: `%Y-%m-%d`
+    pub date_format: String,
+    /// Whether times are shown as `14:02` rather than `02:02pm`
+    pub clock_24h: bool,
+    /// Whether recent times are shown as eg: `3h ago` or `yesterday`
+    pub relative_time: bool,
+    /// Which time is shown in the note list to begin with
+    pub list_timestamp: ListTimestamp,
+    /// Order of the note list to begin with, eg: `modified_desc` or `title`
+    pub sort_order: SortOrder,
+    /// Where daily notes are kept, relative to the thoughts directory
+    pub daily_directory: PathBuf,
+    /// strftime-style format of the file stems of daily notes, which may
+    /// contain `/` to nest them further, eg: `%Y/%m/%Y-%m-%d`
+    pub daily_filename_format: String,
+    /// File new daily notes start out as, relative to the thoughts directory
+    /// unless absolute. Without one, they start out with the date as a heading
+    pub daily_template: Option<PathBuf>,
     pub min_width: u16,
     pub min_height: u16,
     pub react_width: u16,
@@ -64,8 +134,8 @@ impl Config {
         };
 
         match env::var_os("THOUGHTS_CONFIG_PATH") {
-            Some(env_var_path) => return PathBuf::from(env_var_path),
-            None => return config_path,
+            Some(env_var_path) => PathBuf::from(env_var_path),
+            None => config_path,
         }
     }
 
@@ -89,13 +159,43 @@ impl Config {
                 config_proto.temp_file_path,
                 "expected path to temp file",
             )?,
+            index_path: Config::expand_path(
+                config_proto.index_path,
+                "expected path to index file",
+            )?,
             reactive: config_proto.reactive.unwrap(),
+            watch: config_proto.watch.unwrap(),
+            pin_favorites: config_proto.pin_favorites.unwrap(),
+            trash_retention_days: config_proto.trash_retention_days.unwrap(),
+            date_format: Config::check_date_format(config_proto.date_format.unwrap())?,
+            clock_24h: config_proto.clock_24h.unwrap(),
+            relative_time: config_proto.relative_time.unwrap(),
+            list_timestamp: config_proto.list_timestamp.unwrap(),
+            sort_order: config_proto.sort_order.unwrap(),
+            daily_directory: config_proto.daily_directory.unwrap(),
+            daily_filename_format: Config::check_date_format(
+                config_proto.daily_filename_format.unwrap(),
+            )?,
+            daily_template: config_proto
+                .daily_template
+                .map(|path| Config::expand_path(Some(path), "expected path to daily template"))
+                .transpose()?,
             min_width: config_proto.min_width.unwrap(),
             min_height: config_proto.min_height.unwrap(),
             react_width: config_proto.react_width.unwrap(),
         })
     }
 
+    /// Returns an error if `format` isn't a valid strftime-style format,
+    /// as formatting a date with it later would panic
+    fn check_date_format(format: String) -> Result<String> {
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            return Err(anyhow!("invalid date format `{}`", format));
+        }
+
+        Ok(format)
+    }
+
     /// Takes in an Option<PathBuf> and returns either an error,
     /// or an appropriately expanded, OS-specific PathBuf
     /// eg:
@@ -0,0 +1,207 @@
+use crate::config::Config;
+use crate::database::{Database, Entry};
+use crate::templates::{self, Variables};
+use anyhow::{Result, anyhow};
+use chrono::{Local, NaiveDate};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Notes named after the day they are for, eg: `daily/2026-10-17.md`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DailyNotes {
+    directory: PathBuf,
+    filename_format: String,
+    template: Option<PathBuf>,
+}
+
+impl From<&Config> for DailyNotes {
+    fn from(config: &Config) -> DailyNotes {
+        DailyNotes {
+            directory: config.thoughts_path.join(&config.daily_directory),
+            filename_format: config.daily_filename_format.clone(),
+            template: config
+                .daily_template
+                .as_ref()
+                .map(|template| config.thoughts_path.join(template)),
+        }
+    }
+}
+
+impl DailyNotes {
+    /// Where the note for `date` goes, whether or not it exists yet
+    pub fn path(&self, date: NaiveDate) -> PathBuf {
+        self.directory
+            .join(format!("{}.md", date.format(&self.filename_format)))
+    }
+
+    /// The day the note at `path` is for, if it's a daily note at all
+    pub fn date_of(&self, path: &Path) -> Option<NaiveDate> {
+        let name = path.strip_prefix(&self.directory).ok()?.with_extension("");
+
+        NaiveDate::parse_from_str(name.to_str()?, &self.filename_format).ok()
+    }
+
+    /// Returns the path of the note for `date`, which is created from the
+    /// template first if it doesn't exist yet. See `templates::Template` for the variables
+    /// it can contain, `{{date}}` being the day of the note.
+    pub fn open(
+        &self,
+        database: &mut Database,
+        date: NaiveDate,
+        prompt: &mut dyn FnMut(&str) -> Result<String>,
+    ) -> Result<PathBuf> {
+        let path = self.path(date);
+
+        if path.exists() {
+            return Ok(path);
+        }
+
+        let text = match &self.template {
+            Some(template) => {
+                let text = fs::read_to_string(template).map_err(|err| {
+                    anyhow!(
+                        "unable to read daily template {}: {}",
+                        template.display(),
+                        err
+                    )
+                })?;
+
+                let variables = Variables {
+                    title: path
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                    date,
+                    time: Local::now().time(),
+                };
+
+                templates::render(&text, &variables, prompt)?
+            }
+            None => format!("# {}\n", date.format("%A, %B %-d, %Y")),
+        };
+
+        let mut entry = Entry::from_text(path.clone(), &text)?;
+
+        let now = Local::now().timestamp() as u64;
+        entry.set_created(now);
+        entry.set_modified(now);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        database.write_entry(&entry)?;
+        database.poll()?;
+
+        Ok(path)
+    }
+
+    /// The daily note closest to the one at `path`, which comes after
+    /// it when `later` is set and before it otherwise
+    pub fn adjacent<'a>(
+        &self,
+        database: &'a Database,
+        path: &Path,
+        later: bool,
+    ) -> Option<&'a Entry> {
+        let date = self.date_of(path)?;
+
+        let notes = database
+            .entries
+            .iter()
+            .filter(|entry| entry.path.starts_with(&self.directory))
+            .filter_map(|entry| Some((self.date_of(&entry.path)?, entry)));
+
+        match later {
+            true => notes
+                .filter(|(other, _)| *other > date)
+                .min_by_key(|(other, _)| *other),
+            false => notes
+                .filter(|(other, _)| *other < date)
+                .max_by_key(|(other, _)| *other),
+        }
+        .map(|(_, entry)| entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn daily(directory: &Path, filename_format: &str) -> DailyNotes {
+        DailyNotes {
+            directory: directory.join("daily"),
+            filename_format: filename_format.to_string(),
+            template: None,
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn dates_round_trip_through_paths() {
+        let formats = ["%Y-%m-%d", "%d.%m.%Y", "%Y/%m/%d", "%B %-d, %Y", "%Y-%j"];
+
+        for format in formats {
+            let daily = daily(Path::new("/notes"), format);
+            let path = daily.path(date(2026, 3, 7));
+
+            assert_eq!(daily.date_of(&path), Some(date(2026, 3, 7)), "{}", format);
+        }
+    }
+
+    #[test]
+    fn other_notes_have_no_date() {
+        let daily = daily(Path::new("/notes"), "%Y-%m-%d");
+
+        assert_eq!(daily.date_of(Path::new("/notes/2026-03-07.md")), None);
+        assert_eq!(daily.date_of(Path::new("/notes/daily/standup.md")), None);
+        assert_eq!(
+            daily.date_of(Path::new("/notes/daily/2026-03-07 notes.md")),
+            None
+        );
+    }
+
+    #[test]
+    fn adjacent_notes_skip_missing_days() {
+        let directory = TempDir::new().unwrap();
+        let daily = daily(directory.path(), "%Y-%m-%d");
+
+        for (day, text) in [(1, "first"), (4, "second"), (9, "third")] {
+            let path = daily.path(date(2026, 3, day));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+
+        fs::write(directory.path().join("daily/standup.md"), "not daily").unwrap();
+
+        let mut database = Database::open(
+            directory.path().to_path_buf(),
+            directory.path().join("index"),
+        );
+        database.poll().unwrap();
+
+        let title = |later| {
+            daily
+                .adjacent(&database, &daily.path(date(2026, 3, 4)), later)
+                .map(|entry| entry.title.clone())
+        };
+
+        assert_eq!(title(false).as_deref(), Some("2026-03-01"));
+        assert_eq!(title(true).as_deref(), Some("2026-03-09"));
+        assert!(
+            daily
+                .adjacent(&database, &daily.path(date(2026, 3, 9)), true)
+                .is_none()
+        );
+        assert!(
+            daily
+                .adjacent(&database, &directory.path().join("daily/standup.md"), true)
+                .is_none()
+        );
+    }
+}
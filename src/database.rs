@@ -1,14 +1,22 @@
-// TODO: add a custom tagging system so that the user can create their own tags
-// An example of a tag would be "favorite"
-
+use crate::index::Index;
+use crate::query::Query;
+use crate::search::{self, SearchResult};
+use crate::tags;
+use crate::templates::TEMPLATES_DIRECTORY;
+use crate::trash::{Trash, TrashedEntry};
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone};
 use glob::glob;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::{self, File, read_to_string};
 use std::io::Write;
-use std::ops::Not;
-use std::path::PathBuf;
-use std::time::UNIX_EPOCH;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Default, Clone)]
 pub struct Entry {
@@ -16,75 +24,621 @@ pub struct Entry {
     pub favorite: bool,
     pub content: String,
     pub tags: Vec<String>,
+    /// Every other frontmatter key, in the order they were written in,
+    /// eg: `aliases`, or metadata left behind by Obsidian plugins
+    pub frontmatter: Mapping,
     pub path: PathBuf,
+    /// Seconds since the Unix epoch, taken from the `created` frontmatter key
+    /// when present, and otherwise from the file
     pub created_at: u64,
+    /// Seconds since the Unix epoch, taken from the `modified` frontmatter key
+    /// or from the file, whichever is later, so edits made elsewhere still count
     pub modified_at: u64,
+    /// The file as it was when it was read or last written, which unlike
+    /// `modified_at` changes with every write, no matter who made it
+    pub(crate) fingerprint: Option<Fingerprint>,
+    /// The frontmatter as it was read from disk, used to leave it untouched
+    /// when writing the entry back if none of it changed
+    pub(crate) source_frontmatter: Option<SourceFrontmatter>,
+}
+
+impl Entry {
+    /// Parses `text` as the contents of a file at `path`, frontmatter and all.
+    /// The times of the entry are only taken from its frontmatter, and are 0 otherwise
+    pub fn from_text(path: PathBuf, text: &str) -> Result<Entry> {
+        let title = path
+            .file_stem()
+            .ok_or(anyhow!("unable to parse entry name"))?
+            .to_str()
+            .ok_or(anyhow!("unable to convert path to &str"))?
+            .to_string();
+
+        let entry_content_lines = text.split('\n').collect::<Vec<&str>>();
+
+        let (source_frontmatter, content_start) =
+            match Database::parse_frontmatter(&entry_content_lines)? {
+                Some((source_frontmatter, content_start)) => {
+                    (Some(source_frontmatter), content_start)
+                }
+                None => (None, 0),
+            };
+
+        let frontmatter = source_frontmatter
+            .as_ref()
+            .map(|source| source.frontmatter.clone())
+            .unwrap_or_default();
+
+        let content = entry_content_lines[content_start..].join("\n");
+
+        let mut entry = Entry {
+            title,
+            favorite: frontmatter.favorite,
+            content,
+            tags: frontmatter.tags,
+            frontmatter: frontmatter.other,
+            path,
+            source_frontmatter,
+            ..Default::default()
+        };
+
+        entry.created_at = entry.frontmatter_timestamp("created").unwrap_or_default();
+        entry.modified_at = entry.frontmatter_timestamp("modified").unwrap_or_default();
+
+        Ok(entry)
+    }
+
+    /// Returns the frontmatter value of `key`, if it's present and is a `T`
+    pub fn frontmatter_value<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_yaml::from_value(self.frontmatter.get(key)?.clone()).ok()
+    }
+
+    /// Returns the time stored under `key` in seconds since the Unix epoch,
+    /// if it's present and can be parsed. See `parse_timestamp`
+    pub fn frontmatter_timestamp(&self, key: &str) -> Option<u64> {
+        parse_timestamp(&self.frontmatter_value::<String>(key)?)
+    }
+
+    /// Sets when the entry was created, both in `created_at` and in its frontmatter
+    pub fn set_created(&mut self, created_at: u64) {
+        self.created_at = created_at;
+        self.frontmatter.insert(
+            Value::from("created"),
+            Value::from(format_timestamp(created_at)),
+        );
+    }
+
+    /// Sets when the entry was last modified, both in `modified_at` and in its frontmatter
+    pub fn set_modified(&mut self, modified_at: u64) {
+        self.modified_at = modified_at;
+        self.frontmatter.insert(
+            Value::from("modified"),
+            Value::from(format_timestamp(modified_at)),
+        );
+    }
+
+    /// Other names the entry goes by, as used by Obsidian
+    pub fn aliases(&self) -> Vec<String> {
+        self.frontmatter_value::<StringList>("aliases")
+            .map_or(vec![], Vec::from)
+    }
+
+    /// CSS classes Obsidian applies to the entry, older vaults use `cssclass`
+    pub fn css_classes(&self) -> Vec<String> {
+        ["cssclasses", "cssclass"]
+            .into_iter()
+            .filter_map(|key| self.frontmatter_value::<StringList>(key))
+            .flat_map(Vec::from)
+            .collect()
+    }
+
+    /// The entry as it's written to its file, see `Database::write_entry`
+    fn to_text(&self) -> Result<String> {
+        let mut output = String::new();
+
+        let source_text = self
+            .source_frontmatter
+            .as_ref()
+            .and_then(|source| source.text_for(&Frontmatter::from(self)));
+
+        let frontmatter_str = match source_text {
+            Some(text) => Some(text),
+            None => {
+                let mapping = self.frontmatter_mapping();
+
+                // An empty block would be serialized as `{}`, so leave it out entirely
+                match mapping.is_empty() {
+                    true => None,
+                    false => Some(serde_yaml::to_string(&mapping)?),
+                }
+            }
+        };
+
+        if let Some(frontmatter_str) = frontmatter_str {
+            output.push_str("---\n");
+            output.push_str(frontmatter_str.as_str());
+            output.push_str("---\n");
+        }
+
+        output.push_str(&self.content);
+
+        Ok(output)
+    }
+
+    /// Builds the frontmatter to write out, keeping keys which were already
+    /// present where they were, and adding new ones at the end
+    fn frontmatter_mapping(&self) -> Mapping {
+        let favorite = Value::Bool(self.favorite);
+        let tags = Value::Sequence(self.tags.iter().cloned().map(Value::String).collect());
+
+        let source_keys = self
+            .source_frontmatter
+            .iter()
+            .flat_map(|source| source.keys.iter());
+
+        let mut mapping = Mapping::new();
+
+        for key in source_keys {
+            let value = match key.as_str() {
+                Some("favorite") => Some(favorite.clone()),
+                Some("tags") => Some(tags.clone()),
+                _ => self.frontmatter.get(key).cloned(),
+            };
+
+            if let Some(value) = value {
+                mapping.insert(key.clone(), value);
+            }
+        }
+
+        if self.favorite && !mapping.contains_key("favorite") {
+            mapping.insert(Value::from("favorite"), favorite);
+        }
+
+        if !self.tags.is_empty() && !mapping.contains_key("tags") {
+            mapping.insert(Value::from("tags"), tags);
+        }
+
+        for (key, value) in &self.frontmatter {
+            if !mapping.contains_key(key) {
+                mapping.insert(key.clone(), value.clone());
+            }
+        }
+
+        mapping
+    }
 }
 
-impl Into<Frontmatter> for &Entry {
-    fn into(self) -> Frontmatter {
+impl From<&Entry> for Frontmatter {
+    fn from(entry: &Entry) -> Frontmatter {
         Frontmatter {
-            favorite: self.favorite,
-            tags: self.tags.clone(),
+            favorite: entry.favorite,
+            tags: entry.tags.clone(),
+            other: entry.frontmatter.clone(),
         }
     }
 }
 
 // This whole struct is just Serde wizardry
-#[derive(Serialize, Deserialize, Debug)]
-struct Frontmatter {
-    #[serde(skip_serializing_if = "<&bool>::not")]
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub(crate) struct Frontmatter {
     #[serde(default)]
     favorite: bool,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
+    #[serde(deserialize_with = "deserialize_string_list")]
     tags: Vec<String>,
+    #[serde(flatten)]
+    other: Mapping,
+}
+
+/// Frontmatter exactly as it appeared in a file
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SourceFrontmatter {
+    /// Everything between the `---` lines
+    text: String,
+    frontmatter: Frontmatter,
+    /// Every top-level key, in order
+    keys: Vec<Value>,
+}
+
+impl SourceFrontmatter {
+    /// The source text, if it still describes `frontmatter`.
+    ///
+    /// When only the `created` and `modified` keys differ, just the lines they're
+    /// on are replaced, or added at the end, so that comments and formatting
+    /// elsewhere survive. Returns `None` if the frontmatter has to be re-serialized instead.
+    fn text_for(&self, frontmatter: &Frontmatter) -> Option<String> {
+        let mut lines = self
+            .text
+            .split_inclusive('\n')
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+
+        for key in ["created", "modified"] {
+            let value = frontmatter.other.get(key);
+
+            if value == self.frontmatter.other.get(key) {
+                continue;
+            }
+
+            let line = format!("{}: {}", key, value?.as_str()?);
+            let prefix = format!("{}:", key);
+
+            match lines.iter().position(|line| line.starts_with(&prefix)) {
+                Some(index) => {
+                    let ending = match lines[index].ends_with("\r\n") {
+                        true => "\r\n",
+                        false => "\n",
+                    };
+
+                    lines[index] = format!("{}{}", line, ending);
+                }
+                None => lines.push(format!("{}\n", line)),
+            }
+        }
+
+        let text = lines.concat();
+
+        // Anything else that changed, or a value spanning more than one line,
+        // shows up as a difference here
+        let mapping: Mapping = match text.trim().is_empty() {
+            true => Mapping::new(),
+            false => serde_yaml::from_str(&text).ok()?,
+        };
+        let parsed: Frontmatter = serde_yaml::from_value(Value::Mapping(mapping)).ok()?;
+
+        (parsed == *frontmatter).then_some(text)
+    }
+}
+
+/// A list of strings, which may also be written as a single string, eg: `tags: work`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<StringList> for Vec<String> {
+    fn from(list: StringList) -> Vec<String> {
+        match list {
+            StringList::One(string) => vec![string],
+            StringList::Many(strings) => strings,
+        }
+    }
+}
+
+/// Like `StringList`, but a key without a value, eg: `tags:`, is an empty list
+fn deserialize_string_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    Option::<StringList>::deserialize(deserializer).map(|list| list.map_or(vec![], Vec::from))
+}
+
+/// Returned when writing an entry whose file changed on disk since it was read,
+/// as writing it would throw those changes away
+#[derive(Debug)]
+pub struct ConflictError {
+    /// The entry that was going to be written
+    pub entry: Box<Entry>,
+}
+
+impl fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} changed on disk", self.entry.path.display())
+    }
+}
+
+impl std::error::Error for ConflictError {}
+
+/// What changed on disk between two polls
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChangeSet {
+    pub added: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Reads what changed on disk since the database it was made by was last polled,
+/// see `Database::scanner`
+pub struct Scanner {
+    path_str: String,
+    trash_path: PathBuf,
+    templates_path: PathBuf,
+    /// Fingerprints known to the database when the scanner was made
+    fingerprints: HashMap<PathBuf, Fingerprint>,
+}
+
+impl Scanner {
+    /// Finds every file which may be an entry, and reads and parses
+    /// the ones whose fingerprint changed
+    pub fn scan(self) -> Result<Scan> {
+        let mut files = vec![];
+        let mut parsed = HashMap::new();
+
+        for file_path in entry_paths(&self.path_str, self.trash_path, self.templates_path)? {
+            let Ok(fingerprint) = Fingerprint::read(&file_path) else {
+                continue;
+            };
+
+            if self.fingerprints.get(&file_path) != Some(&fingerprint) {
+                parsed.insert(file_path.clone(), Database::parse_entry(file_path.clone()));
+            }
+
+            files.push((file_path, fingerprint));
+        }
+
+        Ok(Scan { files, parsed })
+    }
+}
+
+/// The files found by a `Scanner`, to be brought into the database with `Database::apply`
+pub struct Scan {
+    files: Vec<(PathBuf, Fingerprint)>,
+    /// Files whose fingerprint changed, and what came of parsing them
+    parsed: HashMap<PathBuf, Result<Entry>>,
+}
+
+/// Path of every file matching `path_str` which may be an entry, without reading any of them
+fn entry_paths(
+    path_str: &str,
+    trash_path: PathBuf,
+    templates_path: PathBuf,
+) -> Result<impl Iterator<Item = PathBuf> + use<>> {
+    Ok(glob(path_str)?
+        .filter_map(|file_path| file_path.ok())
+        // Deleted entries aren't entries anymore, and templates aren't entries yet
+        .filter(move |file_path| {
+            !file_path.starts_with(&trash_path) && !file_path.starts_with(&templates_path)
+        }))
+}
+
+/// Cheap way to tell if a file changed without reading it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Fingerprint {
+    modified: SystemTime,
+    size: u64,
+}
+
+impl Fingerprint {
+    fn read(path: &Path) -> Result<Fingerprint> {
+        let metadata = fs::metadata(path)?;
+
+        if !metadata.is_file() {
+            return Err(anyhow!("tried to fingerprint a non-file"));
+        }
+
+        Fingerprint::from_metadata(&metadata)
+    }
+
+    fn from_metadata(metadata: &fs::Metadata) -> Result<Fingerprint> {
+        Ok(Fingerprint {
+            modified: metadata.modified()?,
+            size: metadata.len(),
+        })
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Database {
+    /// Path to directory containing all Thoughts
+    thoughts_path: PathBuf,
     /// Path to directory containing all Thoughts, followed by trailing `/*.md`
     /// Eg: `/home/coal/Important/Vault/Thoughts/*.md`
     path_str: String,
     pub entries: Vec<Entry>,
+    /// Fingerprint of every file seen by the last poll, including ones which
+    /// couldn't be parsed
+    fingerprints: HashMap<PathBuf, Fingerprint>,
+    /// Bumped whenever `entries` change, so that views can tell when to refresh
+    revision: u64,
+    /// Full-text index over the content of `entries`, shared between clones
+    /// so that handing the database around doesn't copy it. Modifying it copies
+    /// it if it's shared, see `update_index`
+    index: Arc<Index>,
+    /// Where the index is stored, until it's read by the first poll
+    index_path: Option<PathBuf>,
+    /// Where deleted entries go
+    trash: Trash,
+    /// Problems found while polling, eg: files which couldn't be parsed,
+    /// kept until they're taken to be shown
+    errors: Vec<String>,
 }
 
 impl Database {
-    /// Scans the filesystem and stores the state in-memory.
+    /// Scans the filesystem and brings the in-memory state up to date.
     /// Expects users to call periodically as to minimize FS-related overhead.
-    /// TODO: think about implementing checksums/hashes to prevent
-    /// excessive re-reads
     ///
-    /// TODO: add proper logging for errors with parsing, instead of ignoring them
-    pub fn poll(&mut self) -> Result<()> {
-        self.entries.clear();
+    /// Only files whose fingerprint changed since the last poll are re-read,
+    /// so polling an unchanged directory only costs a `stat` per file.
+    /// Returns which entries were added, modified or removed.
+    ///
+    /// Files which can't be parsed are left out, see `take_errors`.
+    /// Use `scanner` and `apply` instead to read the files on another thread.
+    pub fn poll(&mut self) -> Result<ChangeSet> {
+        let scan = self.scanner().scan()?;
+
+        Ok(self.apply(scan))
+    }
+
+    /// Everything needed to scan the filesystem for what changed since the last
+    /// poll, which unlike the database can be sent to another thread
+    pub fn scanner(&self) -> Scanner {
+        Scanner {
+            path_str: self.path_str.clone(),
+            trash_path: self.trash.path().to_path_buf(),
+            templates_path: self.thoughts_path.join(TEMPLATES_DIRECTORY),
+            fingerprints: self.fingerprints.clone(),
+        }
+    }
+
+    /// Brings the in-memory state up to date with a scan, see `poll`.
+    ///
+    /// Entries the database wrote itself while the scan was running are kept,
+    /// rather than replaced by what the scan read before they were written.
+    pub fn apply(&mut self, scan: Scan) -> ChangeSet {
+        // The index is only read once it's needed, see `Database::open`
+        if let Some(index_path) = self.index_path.take() {
+            self.index = Arc::new(Index::open(&index_path, &self.thoughts_path));
+        }
+
+        let Scan { files, mut parsed } = scan;
+        let mut previous_entries = self
+            .entries
+            .drain(..)
+            .map(|entry| (entry.path.clone(), entry))
+            .collect::<HashMap<PathBuf, Entry>>();
+        let mut fingerprints = HashMap::new();
+        let mut changes = ChangeSet::default();
+
+        for (file_path, fingerprint) in files {
+            let previous_entry = previous_entries.remove(&file_path);
+            let known_fingerprint = self.fingerprints.get(&file_path).copied();
+
+            let parsed_entry = match parsed.remove(&file_path) {
+                // Already up to date, the database wrote it while the scan was running
+                Some(_) if known_fingerprint == Some(fingerprint) => None,
+                parsed_entry => parsed_entry,
+            };
+
+            let Some(parsed_entry) = parsed_entry else {
+                // Files which failed to parse keep their fingerprint too, so
+                // they aren't retried until they change
+                fingerprints.insert(file_path, known_fingerprint.unwrap_or(fingerprint));
+
+                if let Some(previous_entry) = previous_entry {
+                    self.entries.push(previous_entry);
+                }
 
-        eprintln!("{}", &self.path_str);
-        for file_path in glob(&self.path_str)? {
-            let Ok(file_path) = file_path else {
                 continue;
             };
 
-            let parsed_entry = Database::parse_entry(file_path.clone());
+            fingerprints.insert(file_path.clone(), fingerprint);
 
             let parsed_entry = match parsed_entry {
                 Ok(parsed_entry) => parsed_entry,
                 Err(err) => {
-                    println!("err parsing: {}", err);
+                    self.errors
+                        .push(format!("unable to parse {}: {}", file_path.display(), err));
+
+                    if previous_entry.is_some() {
+                        changes.removed.push(file_path);
+                    }
+
                     continue;
                 }
             };
 
-            //
-            // let Ok(parsed_entry) = Database::parse_entry(file_path.clone()) else {
-            //     continue;
-            // };
+            match previous_entry {
+                Some(_) => changes.modified.push(file_path),
+                None => changes.added.push(file_path),
+            }
 
-            // self.write_entry(&parsed_entry);
             self.entries.push(parsed_entry);
         }
 
-        Ok(())
+        changes.removed.extend(previous_entries.into_keys());
+        changes.removed.sort();
+        self.fingerprints = fingerprints;
+
+        if !changes.is_empty() {
+            self.revision += 1;
+        }
+
+        // Clones of the database share the index, so avoid copying it when
+        // there is nothing to update
+        if !changes.is_empty() || self.index.is_dirty() {
+            self.update_index();
+        }
+
+        changes
+    }
+
+    /// Path of every file which may be an entry, without reading any of them
+    fn entry_paths(&self) -> Result<impl Iterator<Item = PathBuf> + use<>> {
+        entry_paths(
+            &self.path_str,
+            self.trash.path().to_path_buf(),
+            self.thoughts_path.join(TEMPLATES_DIRECTORY),
+        )
+    }
+
+    /// Reads the entry going by `name`, see `find_entry`, without reading every other
+    /// entry like `poll` does. Returns `Ok(None)` if there is no such entry.
+    ///
+    /// Meant for databases which were only opened, see `Database::open`.
+    pub fn load_entry(&mut self, name: &str) -> Result<Option<&Entry>> {
+        let paths = [
+            self.thoughts_path.join(name),
+            self.thoughts_path.join(format!("{}.md", name)),
+        ];
+
+        let path = match self
+            .entry_paths()?
+            .find(|file_path| paths.contains(file_path))
+        {
+            Some(path) => path,
+            None => {
+                let mut matches = self
+                    .entry_paths()?
+                    .filter(|file_path| file_path.file_stem().is_some_and(|stem| stem == name));
+
+                match (matches.next(), matches.next()) {
+                    (Some(path), None) => path,
+                    (Some(_), Some(_)) => {
+                        return Err(anyhow!(
+                            "more than one thought is titled `{}`, use its path instead",
+                            name
+                        ));
+                    }
+                    (None, _) => return Ok(None),
+                }
+            }
+        };
+
+        if let Ok(index) = self.entry_index(&path) {
+            return Ok(Some(&self.entries[index]));
+        }
+
+        let fingerprint = Fingerprint::read(&path)?;
+        let entry = Database::parse_entry(path.clone())?;
+
+        self.fingerprints.insert(path, fingerprint);
+        self.entries.push(entry);
+        self.revision += 1;
+
+        Ok(self.entries.last())
+    }
+
+    fn update_index(&mut self) {
+        // Before the first poll only some entries are known, and the rest would
+        // be dropped from the index
+        if self.index_path.is_some() {
+            return;
+        }
+
+        // The database being polled is the only owner of its index, the TUI takes
+        // it rather than cloning it, so this doesn't copy the index
+        let index = Arc::make_mut(&mut self.index);
+        index.update(&self.entries);
+
+        // The index can always be rebuilt, so failing to store it isn't fatal
+        if let Err(err) = index.save() {
+            self.errors.push(format!("unable to save index: {}", err));
+        }
+    }
+
+    /// Problems found while polling since they were last taken, oldest first
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
     }
 
     // The day that I use regex will be cherished by many
@@ -93,135 +647,1014 @@ impl Database {
             return Err(anyhow!("tried to parse a non-file"));
         }
 
-        let title = file_path
-            .file_stem()
-            .ok_or(anyhow!("unable to parse entry name"))?
-            .to_str()
-            .ok_or(anyhow!("unable to convert path to &str"))?
-            .to_string();
-
-        let created_at = fs::metadata(&file_path)?
-            .created()?
-            .duration_since(UNIX_EPOCH)?
-            .as_secs();
+        let metadata = fs::metadata(&file_path)?;
+        let file_modified_at = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
 
-        let modified_at = fs::metadata(&file_path)?
-            .modified()?
-            .duration_since(UNIX_EPOCH)?
-            .as_secs();
+        // Many filesystems don't record when a file was created
+        let file_created_at = metadata
+            .created()
+            .ok()
+            .and_then(|created| created.duration_since(UNIX_EPOCH).ok())
+            .map_or(file_modified_at, |created| created.as_secs());
 
-        eprintln!("reading file: {}", file_path.to_str().unwrap());
         let entry_content = read_to_string(&file_path)?;
-        let entry_content_lines = entry_content.split('\n').collect::<Vec<&str>>();
+        let mut entry = Entry::from_text(file_path, &entry_content)?;
 
-        if entry_content_lines.len() == 0 {
-            return Err(anyhow!("entry appears to be empty"));
+        // Timestamps in the frontmatter survive being copied around, unlike those of the file
+        if entry.frontmatter_timestamp("created").is_none() {
+            entry.created_at = file_created_at;
         }
 
-        let (frontmatter, content_start) = match Database::parse_frontmatter(&entry_content_lines)?
+        // Editors and sync tools don't update the frontmatter, so a newer file wins
+        entry.modified_at = entry.modified_at.max(file_modified_at);
+
+        entry.fingerprint = Some(Fingerprint::from_metadata(&metadata)?);
+
+        Ok(entry)
+    }
+
+    /// Writes entry to file, replacing the file if it already exists.
+    ///
+    /// Frontmatter which didn't change since it was read is written back as-is,
+    /// as is frontmatter where only its timestamps changed, save for their lines.
+    /// Otherwise it's re-serialized with its keys kept in their original order.
+    ///
+    /// Refuses to replace a file which changed since it was last polled, or which
+    /// the database doesn't know about, by returning a `ConflictError`.
+    /// Use `force_write_entry` to write regardless.
+    pub fn write_entry(&self, entry: &Entry) -> Result<()> {
+        if let Ok(fingerprint) = Fingerprint::read(&entry.path)
+            && self.fingerprints.get(&entry.path) != Some(&fingerprint)
         {
-            Some((frontmatter, content_start)) => (frontmatter, content_start),
-            None => (
-                Frontmatter {
-                    favorite: false,
-                    tags: vec![],
-                },
-                0,
-            ),
-        };
+            return Err(ConflictError {
+                entry: Box::new(entry.clone()),
+            }
+            .into());
+        }
 
-        let content = entry_content_lines[content_start..]
+        self.force_write_entry(entry)
+    }
+
+    /// Like `write_entry`, but replaces the file even if it changed on disk
+    ///
+    /// The file is written next to the original first and then renamed over it,
+    /// so a crash or a full disk halfway through leaves the original intact.
+    pub fn force_write_entry(&self, entry: &Entry) -> Result<()> {
+        write_atomically(&entry.path, entry.to_text()?.as_bytes())
+    }
+
+    /// Searches every entry using the query syntax described on `Query`.
+    /// Results are sorted by relevance and index into `Database::entries`.
+    ///
+    /// Will return an error if the query can't be parsed
+    pub fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        let query = Query::parse(query)?;
+
+        Ok(search::rank(&self.entries, &self.index, &query))
+    }
+
+    /// Like `search`, but only returns entries which also match `filter`
+    pub fn search_filtered(&self, query: &str, filter: &Query) -> Result<Vec<SearchResult>> {
+        let query = Query::And(vec![Query::parse(query)?, filter.clone()]);
+
+        Ok(search::rank(&self.entries, &self.index, &query))
+    }
+
+    /// Returns the entry at `name`, a path relative to the thoughts directory with or
+    /// without the `.md` extension, which may also be given as just its title as long
+    /// as only one entry has that title
+    pub fn find_entry(&self, name: &str) -> Result<&Entry> {
+        let path = self.thoughts_path.join(name);
+        let path_with_extension = self.thoughts_path.join(format!("{}.md", name));
+
+        if let Some(entry) = self
+            .entries
             .iter()
-            .cloned()
-            .collect::<Vec<&str>>()
-            .join("\n");
+            .find(|entry| entry.path == path || entry.path == path_with_extension)
+        {
+            return Ok(entry);
+        }
 
-        Ok(Entry {
-            title,
-            favorite: frontmatter.favorite,
-            content,
-            tags: frontmatter.tags,
-            path: file_path,
-            created_at,
-            modified_at,
-        })
+        let mut matches = self.entries.iter().filter(|entry| entry.title == name);
+
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (Some(_), Some(_)) => Err(anyhow!(
+                "more than one thought is titled `{}`, use its path instead",
+                name
+            )),
+            (None, _) => Err(anyhow!("no thought goes by `{}`", name)),
+        }
     }
 
-    /// Writes entry to file. Will overwrite if file already exists
-    pub fn write_entry(&self, entry: &Entry) -> Result<()> {
-        let mut output = String::new();
+    /// The directory containing every entry
+    pub fn thoughts_path(&self) -> &Path {
+        &self.thoughts_path
+    }
+
+    /// Changes every time the entries do, whether from polling or from edits
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
 
-        output.push_str("---\n");
+    /// Every tag in use, along with how many entries have it. Most used come first
+    pub fn tags(&self) -> Vec<(String, usize)> {
+        // Keyed by the folded tag, so differently cased tags are counted together
+        let mut counts: HashMap<String, (String, usize)> = HashMap::new();
 
-        let frontmatter: Frontmatter = entry.into();
-        let frontmatter_str = serde_yaml::to_string(&frontmatter)?;
+        for tag in self.entries.iter().flat_map(|entry| &entry.tags) {
+            counts
+                .entry(tags::fold(tag))
+                .or_insert_with(|| (tag.clone(), 0))
+                .1 += 1;
+        }
+
+        let mut tags = counts.into_values().collect::<Vec<(String, usize)>>();
+        tags.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
 
-        output.push_str(frontmatter_str.as_str());
-        output.push_str("---\n");
+        tags
+    }
 
-        output.push_str(&entry.content);
+    /// Adds `tag` to the entry at `path` and writes it to disk,
+    /// nothing happens if the entry already has the tag
+    pub fn add_tag(&mut self, path: &Path, tag: &str) -> Result<()> {
+        let tag = tags::normalize(tag)?;
+        let index = self.entry_index(path)?;
 
-        let mut file = File::create(&entry.path)?;
-        file.write_all(output.as_bytes())?;
+        self.update_entry(index, |entry| {
+            if !tags::contains(&entry.tags, &tag) {
+                entry.tags.push(tag);
+            }
+        })?;
 
         Ok(())
     }
 
+    /// Removes `tag` from the entry at `path` and writes it to disk
+    pub fn remove_tag(&mut self, path: &Path, tag: &str) -> Result<()> {
+        let tag = tags::normalize(tag)?;
+        let index = self.entry_index(path)?;
+
+        if !tags::contains(&self.entries[index].tags, &tag) {
+            return Err(anyhow!("entry isn't tagged with `{}`", tag));
+        }
+
+        self.update_entry(index, |entry| {
+            entry.tags.retain(|existing| !tags::is_same(existing, &tag))
+        })?;
+
+        Ok(())
+    }
+
+    /// Flips whether the entry at `path` is a favorite and writes it to disk,
+    /// leaving the content untouched. Returns whether it's now a favorite
+    pub fn toggle_favorite(&mut self, path: &Path) -> Result<bool> {
+        let index = self.entry_index(path)?;
+
+        self.update_entry(index, |entry| entry.favorite = !entry.favorite)?;
+
+        Ok(self.entries[index].favorite)
+    }
+
+    /// Renames the tag `from` to `to` on the entry at `path`, see `rename_tag`
+    pub fn rename_entry_tag(&mut self, path: &Path, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (tags::normalize(from)?, tags::normalize(to)?);
+        let index = self.entry_index(path)?;
+
+        self.update_entry(index, |entry| rename_tags(entry, &from, &to))?;
+
+        Ok(())
+    }
+
+    /// Renames the tag `from` to `to` across every entry, rewriting each affected file.
+    /// Tags nested under `from` are moved along with it, and entries which
+    /// already have `to` simply lose `from`, which makes this a merge as well.
+    ///
+    /// Returns how many entries were changed. Stops at the first entry which
+    /// can't be written, leaving the ones before it changed.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize> {
+        let (from, to) = (tags::normalize(from)?, tags::normalize(to)?);
+        let mut changed = 0;
+
+        for index in 0..self.entries.len() {
+            if self.update_entry(index, |entry| rename_tags(entry, &from, &to))? {
+                changed += 1;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Moves the entry at `path` into the trash, from where it can be restored
+    pub fn delete_entry(&mut self, path: &Path) -> Result<TrashedEntry> {
+        let index = self.entry_index(path)?;
+
+        let trashed_entry = self.trash.put(path)?;
+
+        self.entries.remove(index);
+        self.fingerprints.remove(path);
+        self.revision += 1;
+        self.update_index();
+
+        Ok(trashed_entry)
+    }
+
+    /// Moves `entry` out of the trash and back to where it was deleted from.
+    /// Returns the path of the restored entry.
+    pub fn restore_entry(&mut self, entry: &TrashedEntry) -> Result<PathBuf> {
+        let path = self.trash.restore(entry)?;
+        self.poll()?;
+
+        Ok(path)
+    }
+
+    /// Entries which were deleted, but haven't been purged yet
+    pub fn trash(&self) -> &Trash {
+        &self.trash
+    }
+
+    /// Renames the entry at `path` to `title`, keeping it in the same directory.
+    /// Returns the new path of the entry.
+    ///
+    /// Will return an error if `title` isn't a valid file stem,
+    /// or if another file already goes by that name
+    pub fn rename_entry(&mut self, path: &Path, title: &str) -> Result<PathBuf> {
+        let index = self.entry_index(path)?;
+        let title = validate_title(title)?;
+
+        let new_path = path.with_file_name(format!("{}.md", title));
+        self.relocate_entry(index, new_path)
+    }
+
+    /// Moves the entry at `path` into `directory`, which is relative to the
+    /// thoughts directory and is created if needed. An empty `directory` moves
+    /// the entry to the top of the thoughts directory.
+    /// Returns the new path of the entry.
+    pub fn move_entry(&mut self, path: &Path, directory: &str) -> Result<PathBuf> {
+        let index = self.entry_index(path)?;
+        let directory = PathBuf::from(directory.trim().trim_matches('/'));
+
+        // Keeps entries inside of the thoughts directory, and out of hidden ones
+        for component in directory.components() {
+            match component {
+                Component::Normal(name) if !name.to_string_lossy().starts_with('.') => {}
+                _ => {
+                    return Err(anyhow!(
+                        "`{}` isn't a directory inside of the thoughts directory",
+                        directory.display()
+                    ));
+                }
+            }
+        }
+
+        // Entries in there would be taken for templates, and disappear from the list
+        if directory.starts_with(TEMPLATES_DIRECTORY) {
+            return Err(anyhow!(
+                "`{}` is where templates go, not entries",
+                directory.display()
+            ));
+        }
+
+        let file_name = path
+            .file_name()
+            .ok_or(anyhow!("unable to parse entry name"))?;
+        let directory = self.thoughts_path.join(directory);
+
+        fs::create_dir_all(&directory)?;
+
+        self.relocate_entry(index, directory.join(file_name))
+    }
+
+    /// Moves the file of an entry to `new_path`, refusing to overwrite anything
+    fn relocate_entry(&mut self, index: usize, new_path: PathBuf) -> Result<PathBuf> {
+        let path = self.entries[index].path.clone();
+
+        if new_path == path {
+            return Ok(new_path);
+        }
+
+        if new_path.exists() {
+            return Err(anyhow!("{} already exists", new_path.display()));
+        }
+
+        fs::rename(&path, &new_path)?;
+
+        if let Some(fingerprint) = self.fingerprints.remove(&path) {
+            self.fingerprints.insert(new_path.clone(), fingerprint);
+        }
+
+        let entry = &mut self.entries[index];
+        entry.path = new_path.clone();
+
+        if let Some(stem) = new_path.file_stem() {
+            entry.title = stem.to_string_lossy().to_string();
+        }
+
+        self.revision += 1;
+        self.update_index();
+
+        Ok(new_path)
+    }
+
+    fn entry_index(&self, path: &Path) -> Result<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.path == path)
+            .ok_or(anyhow!("no entry at {}", path.display()))
+    }
+
+    /// Applies `update` to a copy of an entry, and writes it to disk if anything changed.
+    /// Returns whether the entry was written.
+    fn update_entry(&mut self, index: usize, update: impl FnOnce(&mut Entry)) -> Result<bool> {
+        let mut entry = self.entries[index].clone();
+        update(&mut entry);
+
+        let previous = &self.entries[index];
+        if Frontmatter::from(&entry) == Frontmatter::from(previous)
+            && entry.content == previous.content
+        {
+            return Ok(false);
+        }
+
+        entry.set_modified(now());
+
+        // The first write is a good time to hold on to when the entry was created
+        if entry.frontmatter_timestamp("created").is_none() {
+            entry.set_created(entry.created_at);
+        }
+
+        self.write_entry(&entry)?;
+        self.store_written_entry(index, entry);
+
+        Ok(true)
+    }
+
+    /// Marks the entry at `path` as modified just now, eg: after it was edited
+    /// outside of the database
+    pub fn touch_entry(&mut self, path: &Path) -> Result<()> {
+        let index = self.entry_index(path)?;
+
+        self.update_entry(index, |entry| entry.set_modified(now()))?;
+
+        Ok(())
+    }
+
+    /// Stores the creation and modification time of every entry in its frontmatter,
+    /// for entries which don't have them yet. The times are taken from the files,
+    /// so this should happen before they get lost to a sync tool or a checkout.
+    ///
+    /// Returns how many entries were changed. Stops at the first entry which
+    /// can't be written, leaving the ones before it changed.
+    pub fn stamp_timestamps(&mut self) -> Result<usize> {
+        let mut stamped = 0;
+
+        for index in 0..self.entries.len() {
+            let mut entry = self.entries[index].clone();
+
+            if entry.frontmatter_timestamp("created").is_some()
+                && entry.frontmatter_timestamp("modified").is_some()
+            {
+                continue;
+            }
+
+            entry.set_created(entry.created_at);
+            entry.set_modified(entry.modified_at);
+
+            self.write_entry(&entry)?;
+            self.store_written_entry(index, entry);
+            stamped += 1;
+        }
+
+        Ok(stamped)
+    }
+
+    /// Writes `entry` even though its file changed on disk, after a `ConflictError`.
+    /// The entry replaces the one with the same path in memory.
+    pub fn overwrite_entry(&mut self, entry: Entry) -> Result<()> {
+        let index = self.entry_index(&entry.path)?;
+
+        self.force_write_entry(&entry)?;
+        self.store_written_entry(index, entry);
+
+        Ok(())
+    }
+
+    fn store_written_entry(&mut self, index: usize, mut entry: Entry) {
+        // Remember what was just written, so the next poll doesn't read it back in
+        entry.fingerprint = Fingerprint::read(&entry.path).ok();
+
+        if let Some(fingerprint) = entry.fingerprint {
+            self.fingerprints.insert(entry.path.clone(), fingerprint);
+        }
+
+        self.entries[index] = entry;
+        self.revision += 1;
+    }
+
+    /// Returns a path inside of the thoughts directory for a new entry named `title`.
+    ///
+    /// Characters that can't appear in a file name are replaced, and if the file
+    /// stem is already taken a counter is appended to it, eg: `standup 2.md`
+    pub fn available_path(&self, title: &str) -> PathBuf {
+        let stem = sanitize_title(title);
+        let mut path = self.thoughts_path.join(format!("{}.md", stem));
+        let mut counter = 2;
+
+        while path.exists() {
+            path = self.thoughts_path.join(format!("{} {}.md", stem, counter));
+            counter += 1;
+        }
+
+        path
+    }
+
     /// Consumes a file split at newlines characters, and returns a `Result<Option<T>>`
     ///
     /// Result will be Err if frontmatter was detected, but was unable to be parsed,
     /// if no frontmatter was found, then this function will return `Ok(None)`,
     /// if frontmatter was found, and was parsed properly, then the function will return:
-    /// `Ok(Some((Frontmatter, ContentStartIndex)))`
-    fn parse_frontmatter(entry_content_lines: &Vec<&str>) -> Result<Option<(Frontmatter, usize)>> {
+    /// `Ok(Some((SourceFrontmatter, ContentStartIndex)))`
+    fn parse_frontmatter(
+        entry_content_lines: &[&str],
+    ) -> Result<Option<(SourceFrontmatter, usize)>> {
         if entry_content_lines[0] != "---" {
             return Ok(None);
         }
 
-        let mut frontmatter_end: Option<usize> = None;
-
-        for (index, value) in entry_content_lines[1..].iter().enumerate() {
-            if *value == "---" {
-                frontmatter_end = Some(index)
-            }
-        }
-
-        let Some(frontmatter_end) = frontmatter_end else {
+        // The first closing line ends the frontmatter, any later ones are
+        // horizontal rules in the content
+        let Some(frontmatter_end) = entry_content_lines[1..]
+            .iter()
+            .position(|line| *line == "---")
+        else {
             return Err(anyhow!("frontmatter doesn't appear to properly terminate"));
         };
 
-        let frontmatter_str = entry_content_lines[1..=frontmatter_end]
+        let text = entry_content_lines[1..=frontmatter_end]
             .iter()
-            .cloned()
-            .collect::<Vec<&str>>()
-            .join("\n");
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
 
-        eprintln!("{}", frontmatter_str);
-        let frontmatter: Frontmatter = serde_yaml::from_str(&frontmatter_str)?;
+        let mapping: Mapping = match text.trim().is_empty() {
+            true => Mapping::new(),
+            false => serde_yaml::from_str(&text)?,
+        };
+
+        let keys = mapping.keys().cloned().collect();
+        let frontmatter: Frontmatter = serde_yaml::from_value(Value::Mapping(mapping))?;
 
-        Ok(Some((frontmatter, frontmatter_end + 2)))
+        Ok(Some((
+            SourceFrontmatter {
+                text,
+                frontmatter,
+                keys,
+            },
+            frontmatter_end + 2,
+        )))
     }
 
-    /// Creates a new `Database`.
+    /// Creates a new `Database`, and reads every entry.
+    /// Entries which couldn't be read are kept as problems, see `take_errors`.
     ///
-    /// Will scaffold required directories if not already present
-    pub fn new(mut thoughts_path: PathBuf) -> Database {
+    /// Will scaffold required directories if not already present.
+    /// The full-text index is stored at `index_path`, and is rebuilt if it's
+    /// missing or out of date.
+    pub fn new(thoughts_path: PathBuf, index_path: PathBuf) -> Database {
+        let mut database = Database::open(thoughts_path, index_path);
+
+        // TODO: a failed poll on new is an irrecoverable state.
+        // Pretty print errors, please
+        database.poll().unwrap();
+
+        database
+    }
+
+    /// Like `Database::new`, but doesn't read any entries or the index yet, which
+    /// is much quicker for large directories. Entries can be read one at a time
+    /// with `load_entry`, or all at once with `poll`.
+    pub fn open(thoughts_path: PathBuf, index_path: PathBuf) -> Database {
         if !thoughts_path.exists() {
             fs::create_dir_all(&thoughts_path).unwrap();
         }
 
-        thoughts_path.push("**/*.md");
-        let path_str = thoughts_path.to_str().unwrap().to_string();
+        let path_str = thoughts_path.join("**/*.md").to_str().unwrap().to_string();
 
-        let mut database = Database {
+        let trash = Trash::new(&thoughts_path);
+
+        Database {
+            thoughts_path,
             path_str,
             entries: vec![],
-        };
+            fingerprints: HashMap::new(),
+            revision: 0,
+            index: Arc::new(Index::default()),
+            index_path: Some(index_path),
+            trash,
+            errors: vec![],
+        }
+    }
+}
 
-        // TODO: a failed poll on new is an irrecoverable state.
-        // Pretty print errors, please
+/// Characters which aren't allowed in the file stem of an entry
+pub const INVALID_TITLE_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Checks that `title` can be used as a file stem as-is, unlike `sanitize_title`
+/// which quietly fixes it up. Returns the trimmed title.
+fn validate_title(title: &str) -> Result<String> {
+    let title = title.trim();
+
+    if title.is_empty() {
+        return Err(anyhow!("titles can't be empty"));
+    }
+
+    if let Some(char) = title
+        .chars()
+        .find(|char| INVALID_TITLE_CHARS.contains(char))
+    {
+        return Err(anyhow!("titles can't contain `{}`", char));
+    }
+
+    if title.starts_with('.') {
+        return Err(anyhow!("titles can't start with `.`"));
+    }
+
+    Ok(title.to_string())
+}
+
+/// Makes `title` safe to use as a file stem, falling back to "Untitled"
+pub fn sanitize_title(title: &str) -> String {
+    let title = title
+        .trim()
+        .replace(INVALID_TITLE_CHARS, "-")
+        .trim_start_matches('.')
+        .to_string();
+
+    match title.is_empty() {
+        true => "Untitled".to_string(),
+        false => title,
+    }
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    Local::now().timestamp() as u64
+}
+
+/// Formats `timestamp` as ISO 8601 in the local timezone, eg: `2024-06-10T14:02:00+02:00`
+fn format_timestamp(timestamp: u64) -> String {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Parses a timestamp as written in frontmatter into seconds since the Unix epoch.
+///
+/// Accepts ISO 8601 with a timezone, as written by `format_timestamp`,
+/// as well as dates and times without one, eg: `2024-06-10` or `2024-06-10 14:02`,
+/// which are taken to be in the local timezone.
+fn parse_timestamp(text: &str) -> Option<u64> {
+    let text = text.trim();
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return u64::try_from(time.timestamp()).ok();
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .into_iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })?;
+
+    u64::try_from(Local.from_local_datetime(&naive).earliest()?.timestamp()).ok()
+}
+
+/// Replaces the file at `path` with `contents`, by writing them to a temporary file
+/// in the same directory and renaming it over the original. Permissions of the
+/// original are kept.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow!("unable to parse entry name"))?
+        .to_string_lossy();
+    // Hidden and without the `.md` extension, so polling never picks it up
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let write = |temp_path: &Path| -> Result<()> {
+        let mut file = File::create(temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(temp_path, metadata.permissions())?;
+        }
+
+        fs::rename(temp_path, path)?;
+
+        Ok(())
+    };
+
+    if let Err(err) = write(&temp_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+
+    // The rename itself only survives a crash once the directory is synced,
+    // which isn't possible on every platform
+    if let Some(parent) = path.parent()
+        && let Ok(directory) = File::open(parent)
+    {
+        let _ = directory.sync_all();
+    }
+
+    Ok(())
+}
+
+/// Renames `from` to `to` in the tags of `entry`, without introducing duplicates
+fn rename_tags(entry: &mut Entry, from: &str, to: &str) {
+    let mut renamed: Vec<String> = vec![];
+
+    for tag in &entry.tags {
+        let tag = tags::rename(tag, from, to).unwrap_or(tag.clone());
+
+        if !tags::contains(&renamed, &tag) {
+            renamed.push(tag);
+        }
+    }
+
+    entry.tags = renamed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const SOURCE: &str = "---
+# kept by hand
+aliases: [standup, daily sync]
+cssclasses: wide
+tags: [work]
+plugin:
+  nested: 1
+---
+Notes
+";
+
+    fn entry(text: &str) -> Entry {
+        Entry::from_text(PathBuf::from("/notes/standup.md"), text).unwrap()
+    }
+
+    /// A polled database over `notes`, kept in `notes/` inside of the directory,
+    /// with its index next to it
+    fn vault(notes: &[(&str, &str)]) -> (TempDir, Database) {
+        let directory = TempDir::new().unwrap();
+
+        for (name, text) in notes {
+            write(&directory, name, text);
+        }
+
+        let mut database = Database::open(
+            directory.path().join("notes"),
+            directory.path().join("index"),
+        );
         database.poll().unwrap();
 
+        (directory, database)
+    }
+
+    fn write(directory: &TempDir, name: &str, text: &str) -> PathBuf {
+        let path = directory.path().join("notes").join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+
+        path
+    }
+
+    /// Sets the modification time of the file at `path`, so that changes
+    /// are noticed even when they're quicker than the filesystem's clock
+    fn set_modified(path: &Path, seconds: u64) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    fn keys(entry: &Entry) -> Vec<String> {
+        let text = entry.to_text().unwrap();
+        let frontmatter = text.split("---\n").nth(1).unwrap();
+        let mapping: Mapping = serde_yaml::from_str(frontmatter).unwrap();
+
+        mapping
+            .keys()
+            .map(|key| key.as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_entries_are_written_back_byte_for_byte() {
+        assert_eq!(entry(SOURCE).to_text().unwrap(), SOURCE);
+        assert_eq!(entry("Just text\n").to_text().unwrap(), "Just text\n");
+    }
+
+    #[test]
+    fn changed_frontmatter_keeps_unknown_keys_in_order() {
+        let mut entry = entry(SOURCE);
+        entry.favorite = true;
+        entry.tags.push("meetings".to_string());
+
+        assert_eq!(
+            keys(&entry),
+            ["aliases", "cssclasses", "tags", "plugin", "favorite"]
+        );
+
+        let written = Entry::from_text(entry.path.clone(), &entry.to_text().unwrap()).unwrap();
+
+        assert_eq!(written.tags, ["work", "meetings"]);
+        assert_eq!(written.aliases(), ["standup", "daily sync"]);
+        assert_eq!(written.css_classes(), ["wide"]);
+        assert_eq!(written.frontmatter, entry.frontmatter);
+        assert_eq!(written.content, "Notes\n");
+    }
+
+    #[test]
+    fn new_timestamps_only_touch_their_own_lines() {
+        let mut entry = entry(SOURCE);
+        entry.set_created(0);
+        entry.set_modified(0);
+
+        let text = entry.to_text().unwrap();
+        let created = format_timestamp(0);
+
+        assert_eq!(
+            text,
+            SOURCE.replacen(
+                "---\nNotes",
+                &format!("created: {}\nmodified: {}\n---\nNotes", created, created),
+                1
+            )
+        );
+
+        // Changing them again replaces the lines in place
+        let mut entry = Entry::from_text(entry.path.clone(), &text).unwrap();
+        entry.set_modified(60);
+
+        assert_eq!(
+            entry.to_text().unwrap(),
+            text.replacen(
+                &format!("modified: {}", created),
+                &format!("modified: {}", format_timestamp(60)),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn empty_tags_are_an_empty_list() {
+        let entry = entry("---\ntags:\naliases:\n---\nNotes\n");
+
+        assert!(entry.tags.is_empty());
+        assert!(entry.aliases().is_empty());
+        assert_eq!(
+            entry.to_text().unwrap(),
+            "---\ntags:\naliases:\n---\nNotes\n"
+        );
+    }
+
+    #[test]
+    fn single_strings_are_lists_of_one() {
+        assert_eq!(entry("---\ntags: work\n---\n").tags, ["work"]);
+    }
+
+    #[test]
+    fn polling_only_saves_the_index_when_it_changed() {
+        let (directory, mut database) = vault(&[("fruit.md", "apples\n")]);
+        let index_path = directory.path().join("index");
+
+        assert!(index_path.exists());
+        fs::remove_file(&index_path).unwrap();
+
+        database.poll().unwrap();
+        assert!(!index_path.exists());
+
+        // Same length, so only the fingerprint can tell the content apart
+        let path = write(&directory, "fruit.md", "pears!\n");
+        set_modified(&path, 60);
+
+        database.poll().unwrap();
+        assert!(index_path.exists());
+        assert_eq!(database.search("pears").unwrap().len(), 1);
+        assert!(database.search("apples").unwrap().is_empty());
+    }
+
+    #[test]
+    fn scans_keep_entries_written_while_they_ran() {
+        let (directory, mut database) = vault(&[("fruit.md", "apples\n")]);
+        let path = directory.path().join("notes/fruit.md");
+        let scanner = database.scanner();
+
+        database.add_tag(&path, "food").unwrap();
+
+        let changes = database.apply(scanner.scan().unwrap());
+        assert!(changes.is_empty());
+        assert_eq!(database.entries[0].tags, ["food"]);
+        assert_eq!(database.poll().unwrap(), ChangeSet::default());
+    }
+
+    #[test]
+    fn problems_found_when_opening_are_kept() {
+        let directory = TempDir::new().unwrap();
+        write(&directory, "broken.md", "---\ntags: [\n---\n");
+
+        let mut database = Database::new(
+            directory.path().join("notes"),
+            directory.path().join("index"),
+        );
+
+        assert!(database.entries.is_empty());
+        assert!(database.has_errors());
+        assert!(database.take_errors()[0].starts_with("unable to parse"));
+    }
+
+    #[test]
+    fn writes_refuse_to_replace_files_changed_since_they_were_read() {
+        let (directory, database) = vault(&[("fruit.md", "apples\n")]);
+        let mut entry = database.entries[0].clone();
+
+        let path = write(&directory, "fruit.md", "pears\n");
+        set_modified(&path, 60);
+
+        entry.content = "plums\n".to_string();
+        let err = database.write_entry(&entry).unwrap_err();
+
+        assert!(err.downcast_ref::<ConflictError>().is_some());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "pears\n");
+    }
+
+    #[test]
+    fn failed_writes_leave_no_temporary_file_behind() {
+        let directory = TempDir::new().unwrap();
+        // A file can't be renamed over a directory which isn't empty
+        let path = write(&directory, "fruit.md/apples.md", "apples\n");
+        let path = path.parent().unwrap();
+
+        assert!(write_atomically(path, b"pears\n").is_err());
+        assert!(!path.with_file_name(".fruit.md.tmp").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_keep_the_permissions_of_the_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (directory, database) = vault(&[("fruit.md", "apples\n")]);
+        let path = directory.path().join("notes/fruit.md");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let mut entry = database.entries[0].clone();
+        entry.content = "pears\n".to_string();
+        database.force_write_entry(&entry).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "pears\n");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+
+    #[test]
+    fn polls_report_what_changed() {
+        let (directory, mut database) = vault(&[("kept.md", "kept\n"), ("gone.md", "gone\n")]);
+        let notes = directory.path().join("notes");
+
+        assert_eq!(database.poll().unwrap(), ChangeSet::default());
+
+        let changed = write(&directory, "kept.md", "changed\n");
+        set_modified(&changed, 60);
+        write(&directory, "new.md", "new\n");
+        fs::remove_file(notes.join("gone.md")).unwrap();
+
+        assert_eq!(
+            database.poll().unwrap(),
+            ChangeSet {
+                added: vec![notes.join("new.md")],
+                modified: vec![notes.join("kept.md")],
+                removed: vec![notes.join("gone.md")],
+            }
+        );
+        assert_eq!(database.poll().unwrap(), ChangeSet::default());
+    }
+
+    #[test]
+    fn polls_notice_a_new_size_with_the_same_modification_time() {
+        let (directory, mut database) = vault(&[("fruit.md", "apples\n")]);
+        let path = directory.path().join("notes/fruit.md");
+        set_modified(&path, 60);
+        database.poll().unwrap();
+
+        write(&directory, "fruit.md", "apples and pears\n");
+        set_modified(&path, 60);
+
+        assert_eq!(database.poll().unwrap().modified, [path]);
+        assert_eq!(database.entries[0].content, "apples and pears\n");
+    }
+
+    #[test]
+    fn titles_have_to_be_usable_as_file_names() {
+        assert_eq!(validate_title("  standup ").unwrap(), "standup");
+        assert!(validate_title("  ").is_err());
+        assert!(validate_title("work/standup").is_err());
+        assert!(validate_title("standup: monday").is_err());
+        assert!(validate_title(".hidden").is_err());
+    }
+
+    #[test]
+    fn renames_and_moves_refuse_to_replace_other_entries() {
+        let (directory, mut database) = vault(&[
+            ("standup.md", "standup\n"),
+            ("retro.md", "retro\n"),
+            ("work/standup.md", "other standup\n"),
+        ]);
+        let notes = directory.path().join("notes");
+        let path = notes.join("standup.md");
+
+        assert!(database.rename_entry(&path, "retro").is_err());
+        assert!(database.move_entry(&path, "work").is_err());
+        assert_eq!(
+            fs::read_to_string(notes.join("retro.md")).unwrap(),
+            "retro\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("work/standup.md")).unwrap(),
+            "other standup\n"
+        );
+
+        let path = database.rename_entry(&path, "daily").unwrap();
+        assert_eq!(path, notes.join("daily.md"));
+        assert_eq!(
+            database.move_entry(&path, "/work/").unwrap(),
+            notes.join("work/daily.md")
+        );
+    }
+
+    #[test]
+    fn entries_stay_out_of_templates_and_hidden_directories() {
+        let (directory, mut database) = vault(&[("standup.md", "standup\n")]);
+        let path = directory.path().join("notes/standup.md");
+
+        assert!(database.move_entry(&path, TEMPLATES_DIRECTORY).is_err());
+        assert!(
+            database
+                .move_entry(&path, &format!("{}/daily", TEMPLATES_DIRECTORY))
+                .is_err()
+        );
+        assert!(database.move_entry(&path, ".hidden").is_err());
+        assert!(database.move_entry(&path, "../elsewhere").is_err());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn renamed_tags_take_nested_tags_along_and_merge() {
+        let (directory, mut database) = vault(&[
+            ("alpha.md", "---\ntags: [project/alpha, misc]\n---\n"),
+            ("both.md", "---\ntags: [Project, work]\n---\n"),
+            ("other.md", "---\ntags: [projects]\n---\n"),
+        ]);
+        let notes = directory.path().join("notes");
+
+        assert_eq!(database.rename_tag("#project", "work").unwrap(), 2);
+
+        let tags = |database: &Database, name: &str| {
+            let path = notes.join(name);
+            let entry = database.entries.iter().find(|entry| entry.path == path);
+            entry.unwrap().tags.clone()
+        };
+
+        assert_eq!(tags(&database, "alpha.md"), ["work/alpha", "misc"]);
+        assert_eq!(tags(&database, "both.md"), ["work"]);
+        assert_eq!(tags(&database, "other.md"), ["projects"]);
+        assert!(
+            fs::read_to_string(notes.join("alpha.md"))
+                .unwrap()
+                .contains("work/alpha")
+        );
+
         database
+            .rename_entry_tag(&notes.join("alpha.md"), "misc", "work")
+            .unwrap();
+        assert_eq!(tags(&database, "alpha.md"), ["work/alpha", "work"]);
+        assert_eq!(tags(&database, "other.md"), ["projects"]);
     }
 }
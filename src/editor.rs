@@ -0,0 +1,21 @@
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the user's editor on `path`, blocking until it exits.
+///
+/// `editor_command` may contain arguments, eg: `code --wait`, in which case
+/// the path is appended after them.
+/// Expects the terminal to have already been handed back by the TUI.
+pub fn open(editor_command: &str, path: &Path) -> Result<()> {
+    let mut words = editor_command.split_whitespace();
+    let program = words.next().ok_or(anyhow!("editor command is empty"))?;
+
+    let status = Command::new(program).args(words).arg(path).status()?;
+
+    if !status.success() {
+        return Err(anyhow!("editor exited with {}", status));
+    }
+
+    Ok(())
+}
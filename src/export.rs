@@ -0,0 +1,219 @@
+//! Listings of entries, as printed by the `list` and `search` commands.
+//!
+//! Besides the plain text meant for people, entries can be written as `json`,
+//! `ndjson` or `tsv`. Every one of those describes an entry with the same
+//! fields, which only change along with `SCHEMA_VERSION`:
+//!
+//! - `version`: the version of this schema, currently `1`
+//! - `title`: the file stem of the entry
+//! - `path`: absolute path of the file
+//! - `tags`: the tags of the entry, without a leading `#`
+//! - `favorite`: `true` or `false`
+//! - `created_at`, `modified_at`: seconds since the Unix epoch
+//! - `content`: everything after the frontmatter, only included when asked for
+//!
+//! `json` is an array of entries and `ndjson` is one entry per line. `tsv` starts
+//! with a header row naming the columns, leaves out `version`, joins tags with
+//! commas, and escapes tabs, line breaks and backslashes as `\t`, `\n`, `\r` and `\\`.
+
+use crate::database::Entry;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+/// Bumped whenever a field is renamed, removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// An entry as written by `json` and `ndjson`, see the schema at the top of this module.
+/// Kept apart from `Entry`, so that changing the entry can't change the schema
+#[derive(Serialize, Debug)]
+pub struct ExportRecord<'a> {
+    pub version: u32,
+    pub title: &'a str,
+    pub path: &'a Path,
+    pub tags: &'a [String],
+    pub favorite: bool,
+    pub created_at: u64,
+    pub modified_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<&'a str>,
+}
+
+impl<'a> ExportRecord<'a> {
+    pub fn new(entry: &'a Entry, with_content: bool) -> ExportRecord<'a> {
+        ExportRecord {
+            version: SCHEMA_VERSION,
+            title: &entry.title,
+            path: &entry.path,
+            tags: &entry.tags,
+            favorite: entry.favorite,
+            created_at: entry.created_at,
+            modified_at: entry.modified_at,
+            content: with_content.then_some(entry.content.as_str()),
+        }
+    }
+}
+
+/// How a listing of entries is written
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// One line per entry, meant to be read rather than parsed
+    #[default]
+    Text,
+    /// An array of entries
+    Json,
+    /// One entry per line
+    Ndjson,
+    /// Tab separated values, after a header row
+    Tsv,
+}
+
+/// Writes `entries` to `writer` in `format`, along with their content if
+/// `with_content` is set. The content is never part of the plain text format.
+pub fn write(
+    writer: &mut impl Write,
+    entries: &[&Entry],
+    format: Format,
+    with_content: bool,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            for entry in entries {
+                writeln!(writer, "{}", text_line(entry))?;
+            }
+        }
+        Format::Json => {
+            let records = entries
+                .iter()
+                .map(|entry| ExportRecord::new(entry, with_content))
+                .collect::<Vec<ExportRecord>>();
+
+            writeln!(writer, "{}", serde_json::to_string_pretty(&records)?)?;
+        }
+        Format::Ndjson => {
+            for entry in entries {
+                writeln!(
+                    writer,
+                    "{}",
+                    serde_json::to_string(&ExportRecord::new(entry, with_content))?
+                )?;
+            }
+        }
+        Format::Tsv => {
+            let mut header = vec![
+                "title",
+                "path",
+                "tags",
+                "favorite",
+                "created_at",
+                "modified_at",
+            ];
+
+            if with_content {
+                header.push("content");
+            }
+
+            writeln!(writer, "{}", header.join("\t"))?;
+
+            for entry in entries {
+                let mut row = vec![
+                    escape(&entry.title),
+                    escape(&entry.path.to_string_lossy()),
+                    escape(&entry.tags.join(",")),
+                    entry.favorite.to_string(),
+                    entry.created_at.to_string(),
+                    entry.modified_at.to_string(),
+                ];
+
+                if with_content {
+                    row.push(escape(&entry.content));
+                }
+
+                writeln!(writer, "{}", row.join("\t"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Tab separated, eg: `☆ 2026-10-17 09:30 standup #work #daily`
+fn text_line(entry: &Entry) -> String {
+    let modified_at = Local
+        .timestamp_opt(entry.modified_at as i64, 0)
+        .single()
+        .map_or(String::new(), |time| {
+            time.format("%Y-%m-%d %H:%M").to_string()
+        });
+
+    let tags = entry
+        .tags
+        .iter()
+        .map(|tag| format!("#{}", tag))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    format!(
+        "{}\t{}\t{}\t{}",
+        if entry.favorite { "★" } else { "☆" },
+        modified_at,
+        entry.title,
+        tags
+    )
+}
+
+/// Keeps a value within its cell of a tsv row
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn entry() -> Entry {
+        let mut entry = Entry::from_text(
+            PathBuf::from("/notes/standup.md"),
+            "---\ntags: [work, daily]\nfavorite: true\n---\nNotes\n",
+        )
+        .unwrap();
+        entry.created_at = 60;
+        entry.modified_at = 120;
+
+        entry
+    }
+
+    fn ndjson(with_content: bool) -> serde_json::Value {
+        let mut output = vec![];
+        write(&mut output, &[&entry()], Format::Ndjson, with_content).unwrap();
+
+        serde_json::from_slice(&output).unwrap()
+    }
+
+    #[test]
+    fn records_keep_their_shape() {
+        assert_eq!(SCHEMA_VERSION, 1);
+        assert_eq!(
+            ndjson(false),
+            json!({
+                "version": 1,
+                "title": "standup",
+                "path": "/notes/standup.md",
+                "tags": ["work", "daily"],
+                "favorite": true,
+                "created_at": 60,
+                "modified_at": 120,
+            })
+        );
+        assert_eq!(ndjson(true)["content"], "Notes\n");
+    }
+}
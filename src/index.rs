@@ -0,0 +1,243 @@
+use crate::database::{Entry, Fingerprint};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Bump whenever the layout of `Index` or the tokenizer changes,
+/// older indexes are then thrown away and rebuilt
+const VERSION: u32 = 2;
+
+/// Longer tokens are almost always links or encoded data, nobody searches for those
+const MAX_TOKEN_LENGTH: usize = 64;
+
+/// An inverted index over the content of every entry, kept on disk so that
+/// searching doesn't have to go through every file.
+///
+/// Content is split into case-folded alphanumeric tokens, and the position of
+/// every token is stored so that phrases can be matched.
+///
+/// The index is only ever a cache, if it goes missing, can't be read or was
+/// built for another thoughts directory, it's rebuilt from scratch.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Index {
+    version: u32,
+    /// The thoughts directory this index was built for
+    root: PathBuf,
+    /// Indexed by document id, removed documents leave a hole that gets reused
+    documents: Vec<Option<Document>>,
+    postings: HashMap<String, Vec<Posting>>,
+
+    /// Where the index is stored on disk
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    document_ids: HashMap<PathBuf, u32>,
+    /// Ids of removed documents, which can be handed out again
+    #[serde(skip)]
+    free_ids: Vec<u32>,
+    /// Whether there are changes which haven't been saved yet
+    #[serde(skip)]
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Document {
+    path: PathBuf,
+    /// The file as it was when it was indexed, to tell if the entry changed.
+    /// Entries which were never read from a file are always re-indexed
+    fingerprint: Option<Fingerprint>,
+    /// Every distinct token in the document, needed to remove its postings again
+    tokens: Vec<String>,
+}
+
+/// Every occurrence of a token within a single document
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Posting {
+    pub document: u32,
+    /// Positions of the token, counted in tokens from the start of the content
+    pub positions: Vec<u32>,
+}
+
+impl Index {
+    /// Loads the index stored at `path`, falling back to an empty index
+    /// if it doesn't exist or doesn't belong to `root`.
+    pub fn open(path: &Path, root: &Path) -> Index {
+        let index = File::open(path)
+            .ok()
+            .and_then(|file| bincode::deserialize_from::<_, Index>(BufReader::new(file)).ok());
+
+        let mut index = match index {
+            Some(index) if index.version == VERSION && index.root == root => index,
+            _ => Index {
+                version: VERSION,
+                root: root.to_path_buf(),
+                dirty: true,
+                ..Default::default()
+            },
+        };
+
+        index.path = path.to_path_buf();
+        index.document_ids = index
+            .documents
+            .iter()
+            .enumerate()
+            .filter_map(|(id, document)| Some((document.as_ref()?.path.clone(), id as u32)))
+            .collect();
+        index.free_ids = (0..index.documents.len() as u32)
+            .filter(|&id| index.documents[id as usize].is_none())
+            .collect();
+
+        index
+    }
+
+    /// Brings the index in line with `entries`, only re-indexing entries whose
+    /// file changed since they were last indexed, going by their fingerprint
+    pub fn update(&mut self, entries: &[Entry]) {
+        let paths = entries
+            .iter()
+            .map(|entry| &entry.path)
+            .collect::<HashSet<&PathBuf>>();
+
+        let removed = self
+            .document_ids
+            .iter()
+            .filter(|(path, _)| !paths.contains(path))
+            .map(|(_, &id)| id)
+            .collect::<Vec<u32>>();
+
+        for id in removed {
+            self.remove(id);
+        }
+
+        for entry in entries {
+            let is_current = self.document(&entry.path).is_some_and(|document| {
+                document.fingerprint.is_some() && document.fingerprint == entry.fingerprint
+            });
+
+            if !is_current {
+                self.insert(entry);
+            }
+        }
+    }
+
+    /// Writes the index to disk, if anything changed since it was loaded
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Written next to the real file first, so that a crash halfway through
+        // doesn't leave a truncated index behind
+        let temp_path = self.path.with_extension("tmp");
+        bincode::serialize_into(BufWriter::new(File::create(&temp_path)?), self)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    /// Whether the index has changes which haven't been saved yet,
+    /// which is always the case when it was just rebuilt
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Returns the id of the document for the entry at `path`
+    pub fn document_id(&self, path: &Path) -> Option<u32> {
+        self.document_ids.get(path).copied()
+    }
+
+    /// Returns every occurrence of `token`, which is expected to be case-folded
+    pub fn postings(&self, token: &str) -> &[Posting] {
+        self.postings.get(token).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every distinct token in the index, along with its occurrences
+    pub fn tokens(&self) -> impl Iterator<Item = (&str, &[Posting])> {
+        self.postings
+            .iter()
+            .map(|(token, postings)| (token.as_str(), postings.as_slice()))
+    }
+
+    fn document(&self, path: &Path) -> Option<&Document> {
+        self.documents[self.document_id(path)? as usize].as_ref()
+    }
+
+    fn insert(&mut self, entry: &Entry) {
+        if let Some(id) = self.document_id(&entry.path) {
+            self.remove(id);
+        }
+
+        let id = match self.free_ids.pop() {
+            Some(id) => id,
+            None => {
+                self.documents.push(None);
+                (self.documents.len() - 1) as u32
+            }
+        };
+
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+
+        for (position, token) in tokenize(&entry.content) {
+            positions.entry(token).or_default().push(position);
+        }
+
+        let tokens = positions.keys().cloned().collect();
+
+        for (token, positions) in positions {
+            self.postings.entry(token).or_default().push(Posting {
+                document: id,
+                positions,
+            });
+        }
+
+        self.documents[id as usize] = Some(Document {
+            path: entry.path.clone(),
+            fingerprint: entry.fingerprint,
+            tokens,
+        });
+        self.document_ids.insert(entry.path.clone(), id);
+        self.dirty = true;
+    }
+
+    fn remove(&mut self, id: u32) {
+        let Some(document) = self.documents[id as usize].take() else {
+            return;
+        };
+
+        for token in &document.tokens {
+            if let Some(postings) = self.postings.get_mut(token) {
+                postings.retain(|posting| posting.document != id);
+
+                if postings.is_empty() {
+                    self.postings.remove(token);
+                }
+            }
+        }
+
+        self.document_ids.remove(&document.path);
+        self.free_ids.push(id);
+        self.dirty = true;
+    }
+}
+
+/// Splits `text` into case-folded runs of alphanumeric characters, along with
+/// their position, the same way content is split up when it's indexed.
+///
+/// Tokens which are too long to be indexed still take up a position, so that
+/// a phrase doesn't match across one.
+pub fn tokenize(text: &str) -> impl Iterator<Item = (u32, String)> {
+    text.split(|char: char| !char.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .enumerate()
+        .filter(|(_, token)| token.chars().count() <= MAX_TOKEN_LENGTH)
+        .map(|(position, token)| (position as u32, token.to_lowercase()))
+}
@@ -0,0 +1,14 @@
+pub mod capture;
+pub mod config;
+pub mod daily;
+pub mod database;
+pub mod editor;
+pub mod export;
+pub mod index;
+pub mod markdown;
+pub mod query;
+pub mod search;
+pub mod tags;
+pub mod templates;
+pub mod trash;
+pub mod watcher;
@@ -1,28 +1,425 @@
-pub mod config;
-pub mod database;
+mod cli;
 
 use anyhow::Result;
-use config::Config;
-use database::{Database, Entry};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use clap::Parser;
+use cli::{Cli, Command, Filter, Output, TrashCommand};
 use iocraft::prelude::*;
 use std::cmp::{max, min};
+use std::io;
+use std::iter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thoughts::capture::CaptureOptions;
+use thoughts::config::{Config, ListTimestamp};
+use thoughts::daily::DailyNotes;
+use thoughts::database::{ConflictError, Database, Entry};
+use thoughts::markdown::{Line, Span, Style};
+use thoughts::query::Query;
+use thoughts::search::{self, SearchResult, SortOrder};
+use thoughts::tags::{self, TagNode};
+use thoughts::templates::Template;
+use thoughts::trash::TrashedEntry;
+use thoughts::watcher::Watcher;
+use thoughts::{capture, editor, export, markdown};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     let config_path = Config::get_path();
     let config = Config::read(config_path)?;
 
-    println!("{:#?}", config);
+    match cli.command {
+        Some(Command::List { filter, output }) => list_entries(
+            &open_database(&config),
+            "",
+            filter,
+            output,
+            config.sort_order,
+        ),
+        Some(Command::Search {
+            query,
+            filter,
+            output,
+        }) => list_entries(
+            &open_database(&config),
+            &query,
+            filter,
+            output,
+            SortOrder::Relevance,
+        ),
+        Some(Command::Show { name }) => {
+            print!("{}", open_database(&config).find_entry(&name)?.content);
 
-    element! {
-        App(config: config)
+            Ok(())
+        }
+        Some(Command::Edit { name }) => {
+            let mut database = open_database(&config);
+            let path = database.find_entry(&name)?.path.clone();
+            edit_entry(&config, &mut database, &path)
+        }
+        Some(Command::Tag { name, tags }) => {
+            let mut database = open_database(&config);
+            let path = database.find_entry(&name)?.path.clone();
+
+            for tag in tags {
+                match tag.strip_prefix('-') {
+                    Some(tag) => database.remove_tag(&path, tag)?,
+                    None => database.add_tag(&path, &tag)?,
+                }
+            }
+
+            println!("{}", database.find_entry(&name)?.tags.join(" "));
+
+            Ok(())
+        }
+        Some(Command::RenameTag { from, to, only }) => {
+            let mut database = open_database(&config);
+
+            match only {
+                Some(name) => {
+                    let path = database.find_entry(&name)?.path.clone();
+                    database.rename_entry_tag(&path, &from, &to)?;
+                    println!("{}", database.find_entry(&name)?.tags.join(" "));
+                }
+                None => {
+                    let changed = database.rename_tag(&from, &to)?;
+                    println!("renamed `{}` to `{}` on {} thoughts", from, to, changed);
+                }
+            }
+
+            Ok(())
+        }
+        Some(Command::Fav { name }) => {
+            let mut database = open_database(&config);
+            let entry = database.find_entry(&name)?;
+            let (title, path) = (entry.title.clone(), entry.path.clone());
+
+            match database.toggle_favorite(&path)? {
+                true => println!("\"{}\" is now a favorite", title),
+                false => println!("\"{}\" is no longer a favorite", title),
+            }
+
+            Ok(())
+        }
+        Some(Command::Rm { name }) => {
+            let mut database = open_database(&config);
+            let path = database.find_entry(&name)?.path.clone();
+            println!("{}", database.delete_entry(&path)?.id);
+
+            Ok(())
+        }
+        Some(Command::Stats) => print_stats(&open_database(&config)),
+        Some(Command::Today) => edit_daily_note(&config, Local::now().date_naive()),
+        Some(Command::Yesterday) => {
+            let yesterday = Local::now().date_naive() - chrono::Days::new(1);
+            edit_daily_note(&config, yesterday)
+        }
+        Some(Command::Date { date }) => edit_daily_note(&config, date),
+        Some(Command::Add {
+            text,
+            title,
+            tag,
+            favorite,
+            append_to,
+        }) => {
+            let text = match text.as_str() {
+                "-" => io::read_to_string(io::stdin())?,
+                _ => text,
+            };
+
+            let options = CaptureOptions {
+                title,
+                tags: tag,
+                favorite,
+            };
+
+            // Capturing is meant to be quick enough to bind to a global hotkey,
+            // so rather than reading every entry, only the one appended to is read
+            let mut database =
+                Database::open(config.thoughts_path.clone(), config.index_path.clone());
+
+            let entry = match append_to {
+                Some(name) => capture::append_to_thought(&mut database, &name, &text, &options)?,
+                None => capture::add_thought(&database, &text, &options)?,
+            };
+
+            println!("{}", entry.path.display());
+
+            Ok(())
+        }
+        Some(Command::New { template }) => {
+            let template = template
+                .map(|name| Template::find(&config.thoughts_path, &name))
+                .transpose()?;
+
+            match capture::new_thought(&config, &open_database(&config), template.as_ref())? {
+                Some(entry) => println!("{}", entry.path.display()),
+                None => eprintln!("thought was empty, nothing was written"),
+            }
+
+            Ok(())
+        }
+        Some(Command::Migrate) => {
+            let stamped = open_database(&config).stamp_timestamps()?;
+            println!("stamped {} thoughts", stamped);
+
+            Ok(())
+        }
+        Some(Command::Trash { command }) => run_trash(command, open_database(&config)),
+        None => {
+            // Entries which couldn't be read are shown once the TUI is up
+            let database = load_database(&config);
+            run_tui(config, database).await
+        }
+    }
+}
+
+/// Like `load_database`, but prints the entries which couldn't be read
+fn open_database(config: &Config) -> Database {
+    let mut database = load_database(config);
+
+    for error in database.take_errors() {
+        eprintln!("{}", error);
+    }
+
+    database
+}
+
+/// Reads every entry, and empties the trash of anything old enough while at it.
+/// Entries which couldn't be read are left on the database, see `Database::take_errors`
+fn load_database(config: &Config) -> Database {
+    let database = Database::new(config.thoughts_path.clone(), config.index_path.clone());
+
+    if config.trash_retention_days > 0 {
+        let retention = Duration::from_secs(config.trash_retention_days * 24 * 60 * 60);
+
+        if let Err(err) = database.trash().purge_older_than(retention) {
+            eprintln!("unable to empty the trash: {}", err);
+        }
+    }
+
+    database
+}
+
+/// Opens the entry at `path` in the editor, and waits for it to be closed
+fn edit_entry(config: &Config, database: &mut Database, path: &Path) -> Result<()> {
+    editor::open(&config.editor_command, path)?;
+
+    // Editors don't know about the `modified` frontmatter key, so keep it up to date for them
+    if database
+        .poll()?
+        .modified
+        .iter()
+        .any(|modified| modified == path)
+    {
+        database.touch_entry(path)?;
     }
-    .fullscreen()
-    .await?;
 
     Ok(())
 }
 
+/// Opens the daily note for `date` in the editor, after creating it if needed
+fn edit_daily_note(config: &Config, date: NaiveDate) -> Result<()> {
+    let mut database = open_database(config);
+    let path = DailyNotes::from(config).open(&mut database, date, &mut prompt_question)?;
+    edit_entry(config, &mut database, &path)
+}
+
+/// Asks for the value of a `{{prompt:...}}` in a template
+fn prompt_question(question: &str) -> Result<String> {
+    capture::prompt(&format!("{}: ", question))
+}
+
+/// Prints the entries matching `query` and `filter`, see `export` for the formats
+fn list_entries(
+    database: &Database,
+    query: &str,
+    filter: Filter,
+    output: Output,
+    sort_order: SortOrder,
+) -> Result<()> {
+    let mut filters = filter
+        .tag
+        .into_iter()
+        .map(Query::Tag)
+        .collect::<Vec<Query>>();
+
+    if filter.favorites {
+        filters.push(Query::IsFavorite);
+    }
+
+    let mut results = database.search_filtered(query, &Query::And(filters))?;
+    search::sort(
+        &mut results,
+        &database.entries,
+        filter.sort.unwrap_or(sort_order),
+    );
+
+    let entries = results
+        .iter()
+        .map(|result| &database.entries[result.index])
+        .collect::<Vec<&Entry>>();
+
+    let result = export::write(
+        &mut io::stdout().lock(),
+        &entries,
+        output.format,
+        output.content,
+    );
+
+    match result {
+        // Whatever was reading the listing has seen enough, eg: `head`
+        Err(err)
+            if err
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn print_stats(database: &Database) -> Result<()> {
+    let words = database
+        .entries
+        .iter()
+        .map(|entry| entry.content.split_whitespace().count())
+        .sum::<usize>();
+
+    let favorites = database
+        .entries
+        .iter()
+        .filter(|entry| entry.favorite)
+        .count();
+
+    println!("thoughts\t{}", database.entries.len());
+    println!("favorites\t{}", favorites);
+    println!("tags\t{}", database.tags().len());
+    println!("words\t{}", words);
+    println!("trashed\t{}", database.trash().list()?.len());
+
+    Ok(())
+}
+
+fn run_trash(command: TrashCommand, mut database: Database) -> Result<()> {
+    match command {
+        TrashCommand::List => {
+            for entry in database.trash().list()? {
+                let deleted_at = Local
+                    .timestamp_opt(entry.deleted_at as i64, 0)
+                    .single()
+                    .map_or(String::new(), |time| {
+                        time.format("%Y-%m-%d %H:%M").to_string()
+                    });
+
+                println!(
+                    "{}\t{}\t{}",
+                    entry.id,
+                    deleted_at,
+                    entry.original_path.display()
+                );
+            }
+        }
+        TrashCommand::Restore { id } => {
+            let entry = database.trash().find(&id)?;
+            println!("{}", database.restore_entry(&entry)?.display());
+        }
+        TrashCommand::Purge { id: Some(id), .. } => {
+            let entry = database.trash().find(&id)?;
+            database.trash().purge(&entry)?;
+        }
+        TrashCommand::Purge { id: None, .. } => {
+            for entry in database.trash().list()? {
+                database.trash().purge(&entry)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_tui(config: Config, database: Database) -> Result<()> {
+    let mut session = Session {
+        database,
+        ..Default::default()
+    };
+
+    // The editor needs the terminal to itself, so instead of trying to suspend
+    // iocraft we tear the fullscreen session down, run the editor, and start
+    // a fresh session on the same `Database`
+    loop {
+        element! {
+            ContextProvider(value: Context::from_mut(&mut session)) {
+                App(config: config.clone())
+            }
+        }
+        .fullscreen()
+        .await?;
+
+        match session.action.take() {
+            Some(Action::Edit(path)) => {
+                if let Err(err) = edit_entry(&config, &mut session.database, &path) {
+                    session.error = Some(format!("unable to edit thought: {}", err));
+                }
+            }
+            Some(Action::Daily(date)) => {
+                let daily = DailyNotes::from(&config);
+
+                match daily.open(&mut session.database, date, &mut prompt_question) {
+                    Ok(path) => {
+                        session.selected_path = Some(path.clone());
+
+                        if let Err(err) = edit_entry(&config, &mut session.database, &path) {
+                            session.error = Some(format!("unable to edit daily note: {}", err));
+                        }
+                    }
+                    Err(err) => session.error = Some(format!("unable to open daily note: {}", err)),
+                }
+            }
+            Some(Action::New(template)) => {
+                match capture::new_thought(&config, &session.database, template.as_ref()) {
+                    Ok(Some(entry)) => session.selected_path = Some(entry.path),
+                    Ok(None) => {}
+                    Err(err) => session.error = Some(format!("unable to create thought: {}", err)),
+                }
+
+                if let Err(err) = session.database.poll() {
+                    session.error = Some(format!("unable to read thoughts: {}", err));
+                }
+            }
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Work that has to happen outside of the fullscreen session
+enum Action {
+    Edit(PathBuf),
+    /// Open the daily note of a day, creating it if needed
+    Daily(NaiveDate),
+    /// Write a new entry, optionally starting out as a template
+    New(Option<Template>),
+}
+
+/// State that outlives a single fullscreen session.
+/// Provided to the TUI as a mutable context.
+#[derive(Default)]
+struct Session {
+    database: Database,
+    /// Path of the entry that was selected when the last session ended,
+    /// used to restore the selection when a new session starts
+    selected_path: Option<PathBuf>,
+    action: Option<Action>,
+    /// What went wrong while running the last action, shown when the next session starts
+    error: Option<String>,
+}
+
 #[derive(Props, Default)]
 struct AppProps {
     config: Config,
@@ -32,120 +429,1165 @@ struct AppProps {
 fn App(mut hooks: Hooks, props: &AppProps) -> impl Into<AnyElement<'static>> {
     let (width, height) = hooks.use_terminal_size();
     let mut system = hooks.use_context_mut::<SystemContext>();
+    let mut session = hooks.use_context_mut::<Session>();
     let mut should_exit = hooks.use_state(|| false);
 
-    let mut database = hooks.use_state(|| Database::new(props.config.thoughts_path.clone()));
+    // Taken rather than cloned, so that polling doesn't have to copy the index
+    // out from under a second handle to it. It's handed back before exiting
+    let mut database = hooks.use_state(|| std::mem::take(&mut session.database));
+    let mut is_too_small = hooks.use_state(|| false);
+    let selected_path = session.selected_path.clone();
 
+    hooks.use_future({
+        let thoughts_path = props.config.thoughts_path.clone();
+        let watch = props.config.watch;
+
+        async move {
+            let mut watcher = Watcher::new(&thoughts_path, watch);
+
+            loop {
+                watcher.changed().await;
+
+                // Reading the files can take a while, so it happens off of the UI thread
+                let scanner = database.read().scanner();
+
+                // A failed scan is most likely temporary, eg: while a drive is remounted
+                if let Ok(Ok(scan)) = tokio::task::spawn_blocking(|| scanner.scan()).await {
+                    database.write().apply(scan);
+                }
+            }
+        }
+    });
+
+    // The main page handles Esc itself, as it may have to close a prompt first
     hooks.use_terminal_events({
         move |event| match event {
-            TerminalEvent::Key(KeyEvent { code, kind, .. }) if kind != KeyEventKind::Release => {
-                match code {
-                    KeyCode::Esc => should_exit.set(true),
-                    _ => {}
-                }
+            TerminalEvent::Key(KeyEvent { code, kind, .. })
+                if kind != KeyEventKind::Release && code == KeyCode::Esc && is_too_small.get() =>
+            {
+                should_exit.set(true)
             }
             _ => {}
         }
     });
 
-    if should_exit.get() == true {
+    if should_exit.get() {
         system.exit();
     }
 
     let should_render = width >= props.config.min_width && height >= props.config.min_height;
     let show_note_content = width >= props.config.react_width;
 
+    if is_too_small.get() == should_render {
+        is_too_small.set(!should_render);
+    }
+
     element! {
         View(){
             #(match should_render {
-                true => element!{MainPage(term_width: width, term_height: height, show_note_content, database: database.read().clone())}.into_any(),
+                // The database is shared as state, so that pages can edit it
+                true => element!{
+                    ContextProvider(value: Context::owned(database)) {
+                        MainPage(
+                            term_width: width,
+                            term_height: height,
+                            show_note_content,
+                            selected_path,
+                            pin_favorites: props.config.pin_favorites,
+                            timestamp_style: TimestampStyle::from(&props.config),
+                            list_timestamp: props.config.list_timestamp,
+                            sort_order: props.config.sort_order,
+                            daily: DailyNotes::from(&props.config),
+                        )
+                    }
+                }.into_any(),
                 false => element!{ResizeTermPage(term_width: width, term_height: height, min_width: props.config.min_width, min_height: props.config.min_height)}.into_any(),
             })
         }
     }
 }
 
-#[derive(Default, Props)]
-struct MainPageProps {
-    database: Database,
-    show_note_content: bool,
+#[derive(Default, Props)]
+struct MainPageProps {
+    /// Entry to select when the page is first rendered
+    selected_path: Option<PathBuf>,
+    show_note_content: bool,
+    /// List favorites before every other entry
+    pin_favorites: bool,
+    timestamp_style: TimestampStyle,
+    /// Which time to show in the note list to begin with
+    list_timestamp: ListTimestamp,
+    /// Order of the note list to begin with
+    sort_order: SortOrder,
+    daily: DailyNotes,
+    term_width: u16,
+    term_height: u16,
+}
+
+#[component]
+fn MainPage(mut hooks: Hooks, props: &MainPageProps) -> impl Into<AnyElement<'static>> {
+    let mut system = hooks.use_context_mut::<SystemContext>();
+    let mut session = hooks.use_context_mut::<Session>();
+    let mut database_state = *hooks.use_context::<State<Database>>();
+
+    // Polls happen in the background, so their problems are shown here rather
+    // than printed over the screen
+    let has_poll_errors = database_state.read().has_errors();
+    let mut poll_errors = match has_poll_errors {
+        true => database_state.write().take_errors(),
+        false => vec![],
+    };
+    // As are problems with the action run in between sessions, eg: a missing editor
+    poll_errors.extend(session.error.take());
+
+    let database = database_state.read();
+
+    let mut show_tags = hooks.use_state(|| true);
+    let mut tags_visible = hooks.use_state(|| false);
+    let mut is_tags_focused = hooks.use_state(|| false);
+    let mut tag_cursor = hooks.use_state(|| 0);
+    // Every tag in the panel, so the event handler can tell which one the cursor is on
+    let mut tag_paths: State<Vec<String>> = hooks.use_state(Vec::new);
+    let mut selected_tags: State<Vec<String>> = hooks.use_state(Vec::new);
+    let mut match_any_tag = hooks.use_state(|| false);
+
+    // The tag panel goes away along with the content of a note on small terminals
+    if tags_visible.get() != (props.show_note_content && show_tags.get()) {
+        tags_visible.set(props.show_note_content && show_tags.get());
+    }
+
+    if is_tags_focused.get() && !tags_visible.get() {
+        is_tags_focused.set(false);
+    }
+
+    let tag_tree = tags::tree(&database.entries);
+    let paths = tag_tree
+        .iter()
+        .map(|node| node.path.clone())
+        .collect::<Vec<String>>();
+
+    if *tag_paths.read() != paths {
+        tag_paths.set(paths);
+    }
+
+    if tag_cursor.get() >= tag_tree.len() && !tag_tree.is_empty() {
+        tag_cursor.set(tag_tree.len() - 1);
+    }
+
+    // Tags which are no longer in use would filter out everything
+    if selected_tags
+        .read()
+        .iter()
+        .any(|tag| !tag_tree.iter().any(|node| node.path == *tag))
+    {
+        let remaining = selected_tags
+            .read()
+            .iter()
+            .filter(|tag| tag_tree.iter().any(|node| node.path == **tag))
+            .cloned()
+            .collect();
+        selected_tags.set(remaining);
+    }
+
+    let mut favorites_only = hooks.use_state(|| false);
+    let mut filters = vec![];
+
+    if !selected_tags.read().is_empty() {
+        let tags = selected_tags
+            .read()
+            .iter()
+            .cloned()
+            .map(Query::Tag)
+            .collect();
+
+        filters.push(match match_any_tag.get() {
+            true => Query::Or(tags),
+            false => Query::And(tags),
+        });
+    }
+
+    if favorites_only.get() {
+        filters.push(Query::IsFavorite);
+    }
+
+    let mut query = hooks.use_state(String::new);
+    let search = match filters.is_empty() {
+        true => database.search(&query.read()),
+        false => database.search_filtered(&query.read(), &Query::And(filters)),
+    };
+    let (mut results, query_error) = match search {
+        Ok(results) => (results, None),
+        Err(err) => (vec![], Some(err.to_string())),
+    };
+
+    let mut sort_order = hooks.use_state(|| props.sort_order);
+    search::sort(&mut results, &database.entries, sort_order.get());
+
+    // Stable, so that favorites keep their order amongst themselves
+    if props.pin_favorites {
+        results.sort_by_key(|result| !database.entries[result.index].favorite);
+    }
+
+    let position_of = |path: &PathBuf| {
+        results
+            .iter()
+            .position(|result| &database.entries[result.index].path == path)
+    };
+
+    // Indexes into `results` rather than `Database::entries`
+    let mut selected_entry: State<Option<usize>> =
+        hooks.use_state(|| props.selected_path.as_ref().and_then(position_of));
+    // Path of the selected entry as of the last render
+    let mut selected_path = hooks.use_state(|| props.selected_path.clone());
+    let mut seen_revision = hooks.use_state(|| database.revision());
+    let mut seen_sort_order = hooks.use_state(|| sort_order.get());
+    let mut should_edit = hooks.use_state(|| false);
+    let mut should_open_today = hooks.use_state(|| false);
+    // Entry to select once it's found in the results, eg: the next daily note
+    let mut follow_path: State<Option<PathBuf>> = hooks.use_state(|| None);
+    let mut should_create = hooks.use_state(|| false);
+    // Read when the new note is asked for, rather than on every render
+    let mut templates: State<Vec<Template>> = hooks.use_state(Vec::new);
+    // Position in `templates`, after the blank note which comes first
+    let mut template_cursor: State<usize> = hooks.use_state(|| 0);
+    let mut chosen_template: State<Option<Template>> = hooks.use_state(|| None);
+    let mut should_exit = hooks.use_state(|| false);
+    let mut mode = hooks.use_state(|| Mode::Search);
+    let mut tag_error: State<Option<String>> = hooks.use_state(|| None);
+    // Text typed into the rename and move prompts, which are handled here
+    // rather than by their own component
+    let mut prompt_input = hooks.use_state(String::new);
+    let mut prompt_cursor = hooks.use_state(|| 0);
+    // Outcome of the last action, cleared by the next key press
+    let mut toast: State<Option<Toast>> = hooks.use_state(|| None);
+    if let Some(error) = poll_errors.last() {
+        toast.set(Some(Toast::error(match poll_errors.len() {
+            1 => error.clone(),
+            count => format!("{}, and {} more problems", error, count - 1),
+        })));
+    }
+    // Entry which couldn't be written because its file changed on disk
+    let mut conflict: State<Option<Entry>> = hooks.use_state(|| None);
+    let mut list_timestamp = hooks.use_state(|| props.list_timestamp);
+    // Read when the trash is opened, rather than on every render
+    let mut trash_entries: State<Vec<TrashedEntry>> = hooks.use_state(Vec::new);
+    let mut trash_cursor: State<usize> = hooks.use_state(|| 0);
+
+    // The event handler below is only created once, so it has to read the
+    // amount of entries through state rather than capturing it
+    let mut entry_count = hooks.use_state(|| 0);
+    if entry_count.get() != results.len() {
+        entry_count.set(results.len());
+    }
+
+    // Entries may have been added, removed, renamed on disk or re-sorted, so the
+    // selection follows the selected entry rather than staying at the same position
+    if seen_revision.get() != database.revision() || seen_sort_order.get() != sort_order.get() {
+        seen_revision.set(database.revision());
+        seen_sort_order.set(sort_order.get());
+
+        if let Some(index) = selected_path.read().as_ref().and_then(position_of) {
+            selected_entry.set(Some(index));
+        }
+    }
+
+    let followed_path = follow_path.read().clone();
+    if let Some(path) = followed_path {
+        follow_path.set(None);
+
+        match position_of(&path) {
+            Some(index) => selected_entry.set(Some(index)),
+            None => toast.set(Some(Toast::error(format!(
+                "{} is hidden by the search",
+                path.file_stem().unwrap_or_default().to_string_lossy()
+            )))),
+        }
+    }
+
+    match selected_entry.get() {
+        Some(_) if results.is_empty() => selected_entry.set(None),
+        None if results.is_empty() => {}
+        None => selected_entry.set(Some(0)),
+        Some(index) if index >= results.len() => selected_entry.set(Some(results.len() - 1)),
+        Some(_) => {}
+    }
+
+    // Only the rows in view are rendered, which keeps large lists responsive. Leaves room
+    // for the status bar, the search bar, and the borders of the pane.
+    let rows = max(props.term_height.saturating_sub(6) as usize, 1);
+    let mut list_rows = hooks.use_state(|| rows);
+    if list_rows.get() != rows {
+        list_rows.set(rows);
+    }
+
+    // Index into `results` of the first row in view, which scrolls no further
+    // than is needed to keep the selected entry in view
+    let mut list_scroll = hooks.use_state(|| 0);
+    let scroll = match selected_entry.get() {
+        Some(index) if index < list_scroll.get() => index,
+        Some(index) if index >= list_scroll.get() + rows => index + 1 - rows,
+        _ => list_scroll.get(),
+    }
+    .min(results.len().saturating_sub(rows));
+
+    if list_scroll.get() != scroll {
+        list_scroll.set(scroll);
+    }
+
+    hooks.use_terminal_events({
+        // Comes from the config, so it won't change for as long as the page is shown
+        let daily = props.daily.clone();
+
+        move |event| match event {
+            TerminalEvent::Key(KeyEvent {
+                code,
+                kind,
+                modifiers,
+                ..
+            }) if kind != KeyEventKind::Release => {
+                let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+                // Alt along with the arrow or paging keys scrolls the content of the note instead
+                let alt = modifiers.contains(KeyModifiers::ALT);
+
+                if toast.read().is_some() {
+                    toast.set(None);
+                }
+
+                match code {
+                    KeyCode::Esc if mode.get() != Mode::Search => mode.set(Mode::Search),
+                    KeyCode::Esc => should_exit.set(true),
+                    // The tag bar has the keyboard to itself while it's open
+                    _ if mode.get() == Mode::Tag => {}
+                    _ if mode.get() == Mode::ConfirmDelete => {
+                        if !matches!(code, KeyCode::Enter | KeyCode::Char('y')) {
+                            mode.set(Mode::Search);
+                            return;
+                        }
+
+                        let Some(path) = selected_path.read().clone() else {
+                            return;
+                        };
+
+                        match database_state.write().delete_entry(&path) {
+                            Ok(trashed_entry) => toast.set(Some(Toast::info(format!(
+                                "Moved \"{}\" to the trash, Ctrl+Z to restore",
+                                trashed_entry.title()
+                            )))),
+                            Err(err) => {
+                                toast.set(Some(Toast::error(format!("unable to delete: {}", err))))
+                            }
+                        }
+
+                        mode.set(Mode::Search);
+                    }
+                    _ if mode.get() == Mode::Conflict => {
+                        let Some(entry) = conflict.read().clone() else {
+                            return;
+                        };
+
+                        let result = match code {
+                            // Reading the file again picks up whatever changed on disk
+                            KeyCode::Char('r') => database_state.write().poll().map(|_| "Reloaded"),
+                            KeyCode::Char('o') => database_state
+                                .write()
+                                .overwrite_entry(entry)
+                                .map(|_| "Overwritten"),
+                            _ => Ok("Nothing was written"),
+                        };
+
+                        match result {
+                            Ok(outcome) => toast.set(Some(Toast::info(outcome.to_string()))),
+                            Err(err) => toast.set(Some(Toast::error(err.to_string()))),
+                        }
+
+                        conflict.set(None);
+                        mode.set(Mode::Search);
+                    }
+                    _ if mode.get() == Mode::Template => match code {
+                        KeyCode::Up => template_cursor.set(template_cursor.get().saturating_sub(1)),
+                        KeyCode::Down => template_cursor
+                            .set(min(template_cursor.get() + 1, templates.read().len())),
+                        KeyCode::Enter => {
+                            let template = match template_cursor.get() {
+                                0 => None,
+                                cursor => templates.read().get(cursor - 1).cloned(),
+                            };
+
+                            chosen_template.set(template);
+                            should_create.set(true);
+                            mode.set(Mode::Search);
+                        }
+                        _ => {}
+                    },
+                    _ if mode.get() == Mode::Trash => match code {
+                        KeyCode::Up => trash_cursor.set(trash_cursor.get().saturating_sub(1)),
+                        KeyCode::Down => trash_cursor.set(min(
+                            trash_cursor.get() + 1,
+                            trash_entries.read().len().saturating_sub(1),
+                        )),
+                        KeyCode::Enter => {
+                            let Some(entry) = trash_entries.read().get(trash_cursor.get()).cloned()
+                            else {
+                                return;
+                            };
+
+                            match database_state.write().restore_entry(&entry) {
+                                Ok(path) => {
+                                    selected_path.set(Some(path));
+                                    toast.set(Some(Toast::info(format!(
+                                        "Restored \"{}\"",
+                                        entry.title()
+                                    ))));
+                                    mode.set(Mode::Search);
+                                }
+                                Err(err) => toast
+                                    .set(Some(Toast::error(format!("unable to restore: {}", err)))),
+                            }
+                        }
+                        _ => {}
+                    },
+                    KeyCode::Enter if matches!(mode.get(), Mode::Rename | Mode::Move) => {
+                        let Some(path) = selected_path.read().clone() else {
+                            return;
+                        };
+
+                        let input = prompt_input.to_string();
+                        let (result, verb) = match mode.get() {
+                            Mode::Rename => (
+                                database_state.write().rename_entry(&path, &input),
+                                "Renamed",
+                            ),
+                            _ => (database_state.write().move_entry(&path, &input), "Moved"),
+                        };
+
+                        // The prompt stays open on errors, so the input can be fixed
+                        match result {
+                            Ok(new_path) => {
+                                selected_path.set(Some(new_path.clone()));
+                                toast.set(Some(Toast::info(format!(
+                                    "{} to {}",
+                                    verb,
+                                    new_path.display()
+                                ))));
+                                mode.set(Mode::Search);
+                            }
+                            Err(err) => toast.set(Some(Toast::error(err.to_string()))),
+                        }
+                    }
+                    _ if matches!(mode.get(), Mode::Rename | Mode::Move) => {
+                        let mut chars = prompt_input.read().chars().collect::<Vec<char>>();
+                        let mut cursor = prompt_cursor.get();
+
+                        if edit_line(&mut chars, &mut cursor, code, modifiers) {
+                            prompt_input.set(chars.into_iter().collect());
+                        }
+
+                        prompt_cursor.set(cursor);
+                    }
+                    KeyCode::Char('b') if ctrl => show_tags.set(!show_tags.get()),
+                    KeyCode::Char('o') if ctrl => match_any_tag.set(!match_any_tag.get()),
+                    KeyCode::Tab if tags_visible.get() => {
+                        is_tags_focused.set(!is_tags_focused.get())
+                    }
+                    KeyCode::Up if is_tags_focused.get() => {
+                        tag_cursor.set(tag_cursor.get().saturating_sub(1))
+                    }
+                    KeyCode::Down if is_tags_focused.get() => tag_cursor.set(min(
+                        tag_cursor.get() + 1,
+                        tag_paths.read().len().saturating_sub(1),
+                    )),
+                    KeyCode::Enter if is_tags_focused.get() => {
+                        let Some(tag) = tag_paths.read().get(tag_cursor.get()).cloned() else {
+                            return;
+                        };
+
+                        let mut tags = selected_tags.read().clone();
+
+                        match tags.contains(&tag) {
+                            true => tags.retain(|selected| *selected != tag),
+                            false => tags.push(tag),
+                        }
+
+                        selected_tags.set(tags);
+                    }
+                    KeyCode::Up if !alt => {
+                        if let Some(index) = selected_entry.get() {
+                            selected_entry.set(Some(max(index as i32 - 1, 0) as usize))
+                        }
+                    }
+                    KeyCode::Down if !alt => {
+                        if let Some(index) = selected_entry.get() {
+                            selected_entry
+                                .set(Some(
+                                    min(index as i32 + 1, entry_count.get() as i32 - 1) as usize
+                                ))
+                        }
+                    }
+                    KeyCode::PageUp if !alt => {
+                        if let Some(index) = selected_entry.get() {
+                            selected_entry.set(Some(index.saturating_sub(list_rows.get())))
+                        }
+                    }
+                    KeyCode::PageDown if !alt => {
+                        if let Some(index) = selected_entry.get() {
+                            selected_entry.set(Some(min(
+                                index + list_rows.get(),
+                                entry_count.get().saturating_sub(1),
+                            )))
+                        }
+                    }
+                    KeyCode::Home if entry_count.get() > 0 => selected_entry.set(Some(0)),
+                    KeyCode::End if entry_count.get() > 0 => {
+                        selected_entry.set(Some(entry_count.get() - 1))
+                    }
+                    KeyCode::Enter => should_edit.set(true),
+                    KeyCode::Char('n') if ctrl => {
+                        let thoughts_path = database_state.read().thoughts_path().to_path_buf();
+
+                        match Template::list(&thoughts_path) {
+                            // Straight to the editor when there is nothing to pick from
+                            Ok(listed) if listed.is_empty() => {
+                                chosen_template.set(None);
+                                should_create.set(true);
+                            }
+                            Ok(listed) => {
+                                templates.set(listed);
+                                template_cursor.set(0);
+                                mode.set(Mode::Template);
+                            }
+                            Err(err) => toast.set(Some(Toast::error(format!(
+                                "unable to read the templates: {}",
+                                err
+                            )))),
+                        }
+                    }
+                    KeyCode::Char('f') if ctrl => {
+                        let Some(path) = selected_path.read().clone() else {
+                            return;
+                        };
+
+                        // Written straight away, the database is updated in place so
+                        // there is no need to wait for the next poll
+                        if let Err(err) = database_state.write().toggle_favorite(&path) {
+                            match err.downcast_ref::<ConflictError>() {
+                                Some(conflict_error) => {
+                                    conflict.set(Some(*conflict_error.entry.clone()));
+                                    mode.set(Mode::Conflict);
+                                }
+                                None => toast.set(Some(Toast::error(err.to_string()))),
+                            }
+                        }
+                    }
+                    KeyCode::Char('s') if ctrl => favorites_only.set(!favorites_only.get()),
+                    KeyCode::Char('l') if ctrl => sort_order.set(sort_order.get().next()),
+                    KeyCode::Char('e') if ctrl => {
+                        let (shown, message) = match list_timestamp.get() {
+                            ListTimestamp::Created => {
+                                (ListTimestamp::Modified, "Showing when notes were modified")
+                            }
+                            ListTimestamp::Modified => {
+                                (ListTimestamp::Created, "Showing when notes were created")
+                            }
+                        };
+
+                        list_timestamp.set(shown);
+                        toast.set(Some(Toast::info(message.to_string())));
+                    }
+                    KeyCode::Char('t') if ctrl => {
+                        tag_error.set(None);
+                        mode.set(Mode::Tag);
+                    }
+                    KeyCode::Char('d') if ctrl => mode.set(Mode::ConfirmDelete),
+                    KeyCode::Char('y') if ctrl => should_open_today.set(true),
+                    KeyCode::Char(char @ (',' | '.')) if modifiers.contains(KeyModifiers::ALT) => {
+                        let Some(path) = selected_path.read().clone() else {
+                            return;
+                        };
+
+                        let later = char == '.';
+                        let database = database_state.read();
+
+                        match daily.adjacent(&database, &path, later) {
+                            Some(entry) => follow_path.set(Some(entry.path.clone())),
+                            None if daily.date_of(&path).is_none() => {
+                                toast.set(Some(Toast::info("Not a daily note".to_string())))
+                            }
+                            None => toast.set(Some(Toast::info(format!(
+                                "No {} daily note",
+                                if later { "later" } else { "earlier" }
+                            )))),
+                        }
+                    }
+                    KeyCode::Char('z') if ctrl => match database_state.read().trash().list() {
+                        Ok(entries) => {
+                            trash_entries.set(entries);
+                            trash_cursor.set(0);
+                            mode.set(Mode::Trash);
+                        }
+                        Err(err) => toast.set(Some(Toast::error(format!(
+                            "unable to read the trash: {}",
+                            err
+                        )))),
+                    },
+                    KeyCode::Char('r') if ctrl => {
+                        let Some(path) = selected_path.read().clone() else {
+                            return;
+                        };
+
+                        let title = path
+                            .file_stem()
+                            .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+
+                        prompt_cursor.set(title.chars().count());
+                        prompt_input.set(title);
+                        mode.set(Mode::Rename);
+                    }
+                    KeyCode::Char('x') if ctrl => {
+                        let Some(path) = selected_path.read().clone() else {
+                            return;
+                        };
+
+                        // Starts out with the directory the entry is already in
+                        let directory = path
+                            .parent()
+                            .and_then(|parent| {
+                                parent
+                                    .strip_prefix(database_state.read().thoughts_path())
+                                    .ok()
+                            })
+                            .map_or(String::new(), |directory| {
+                                directory.to_string_lossy().to_string()
+                            });
+
+                        prompt_cursor.set(directory.chars().count());
+                        prompt_input.set(directory);
+                        mode.set(Mode::Move);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+
+    let selected = selected_entry
+        .get()
+        .and_then(|index| results.get(index))
+        .map(|result| &database.entries[result.index]);
+
+    if selected_path.read().as_ref() != selected.map(|entry| &entry.path) {
+        selected_path.set(selected.map(|entry| entry.path.clone()));
+    }
+
+    if should_exit.get() {
+        system.exit();
+    }
+
+    // Every prompt acts on the selected entry
+    if !matches!(mode.get(), Mode::Search | Mode::Trash | Mode::Template) && selected.is_none() {
+        mode.set(Mode::Search);
+    }
+
+    if should_edit.get() {
+        should_edit.set(false);
+
+        if let Some(entry) = selected {
+            session.database = database.clone();
+            session.selected_path = Some(entry.path.clone());
+            session.action = Some(Action::Edit(entry.path.clone()));
+            system.exit();
+        }
+    }
+
+    if should_open_today.get() {
+        should_open_today.set(false);
+
+        session.database = database.clone();
+        session.selected_path = selected.map(|entry| entry.path.clone());
+        session.action = Some(Action::Daily(Local::now().date_naive()));
+        system.exit();
+    }
+
+    // The daily notes either side of the selected one, if it's a daily note
+    let (previous_day, next_day) = match selected {
+        Some(entry) if props.daily.date_of(&entry.path).is_some() => (
+            props
+                .daily
+                .adjacent(&database, &entry.path, false)
+                .map(|entry| entry.title.clone()),
+            props
+                .daily
+                .adjacent(&database, &entry.path, true)
+                .map(|entry| entry.title.clone()),
+        ),
+        _ => (None, None),
+    };
+
+    let tagged_path = selected.map(|entry| entry.path.clone());
+
+    // Leaves room for the status bar, the search bar, and the borders of the pane
+    let note_content_height = props.term_height.saturating_sub(6);
+
+    // Manually adjusting width of the containers isn't a great idea, but I can't
+    // seem to get flexboxes to play nicely at the moment
+    let tag_panel_width = match tags_visible.get() {
+        true => TAG_PANEL_WIDTH,
+        false => 0,
+    };
+    let note_list_width = match props.show_note_content {
+        true => (props.term_width - 2 - tag_panel_width) / 2,
+        false => props.term_width - 2,
+    };
+
+    if should_create.get() {
+        should_create.set(false);
+
+        session.database = database.clone();
+        session.selected_path = selected.map(|entry| entry.path.clone());
+        session.action = Some(Action::New(chosen_template.read().clone()));
+        system.exit();
+    }
+
+    element! {
+        View(
+            display: Display::Flex,
+            height: props.term_height,
+            width: props.term_width,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceBetween,
+            padding_left: 1,
+            padding_right: 1,
+        ) {
+            StatusBar(toast: toast.read().clone(), sort_order: sort_order.get())
+
+            View(
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                height: 100pct,
+                height: props.term_height,
+                width: props.term_width - 2,
+                ) {
+                #(match tags_visible.get() {
+                    true => element!{TagPanel(
+                        width: tag_panel_width,
+                        height: note_content_height,
+                        nodes: tag_tree,
+                        cursor: is_tags_focused.get().then_some(tag_cursor.get()),
+                        selected: selected_tags.read().clone(),
+                        match_any: match_any_tag.get(),
+                    )}.into_any(),
+                    false => element!{View}.into_any(),
+                })
+
+                #(match mode.get() {
+                    Mode::Trash => element!{TrashList(
+                        width: props.term_width - 2 - tag_panel_width,
+                        height: note_content_height,
+                        entries: trash_entries.read().clone(),
+                        cursor: trash_cursor.get(),
+                    )}.into_any(),
+                    _ => element!{
+                        View(width: props.term_width - 2 - tag_panel_width) {
+                            NoteList(
+                                width: note_list_width,
+                                height: rows,
+                                rows: results
+                                    .iter()
+                                    .skip(scroll)
+                                    .take(rows)
+                                    .map(|result| (database.entries[result.index].clone(), result.clone()))
+                                    .collect::<Vec<(Entry, SearchResult)>>(),
+                                first_row: scroll,
+                                total: results.len(),
+                                selected_entry: selected_entry.get(),
+                                timestamp_style: props.timestamp_style.clone(),
+                                list_timestamp: list_timestamp.get(),
+                                rename: (mode.get() == Mode::Rename).then(|| (prompt_input.to_string(), prompt_cursor.get())),
+                            )
+
+                            // Hide the content of a note if the terminal is smaller than
+                            // or equal to the react width set through the config
+                            #(match props.show_note_content {
+                                    true => element!{NoteContent(
+                                        width: props.term_width - 2 - tag_panel_width - note_list_width,
+                                        height: note_content_height,
+                                        entry: selected.cloned(),
+                                        is_daily: selected.is_some_and(|entry| props.daily.date_of(&entry.path).is_some()),
+                                        previous_day,
+                                        next_day,
+                                    )}.into_any(),
+                                    false => element!{View}.into_any(),
+                            })
+                        }
+                    }.into_any(),
+                })
+            }
+
+            #(match mode.get() {
+                Mode::Tag => element!{TagBar(
+                    tags: database.tags().into_iter().map(|(tag, _)| tag).collect::<Vec<String>>(),
+                    entry_tags: selected.map(|entry| entry.tags.clone()).unwrap_or_default(),
+                    error: tag_error.read().clone(),
+                    on_submit: move |input: String| {
+                        let Some(path) = &tagged_path else {
+                            return;
+                        };
+
+                        // Several tags can be given at once, and a leading `-` removes a tag
+                        let result = input
+                            .split([',', ' '])
+                            .filter(|tag| !tag.is_empty())
+                            .try_for_each(|tag| match tag.strip_prefix('-') {
+                                Some(tag) => database_state.write().remove_tag(path, tag),
+                                None => database_state.write().add_tag(path, tag),
+                            });
+
+                        match result {
+                            Ok(()) => mode.set(Mode::Search),
+                            Err(err) => match err.downcast_ref::<ConflictError>() {
+                                Some(conflict_error) => {
+                                    conflict.set(Some(*conflict_error.entry.clone()));
+                                    mode.set(Mode::Conflict);
+                                }
+                                None => tag_error.set(Some(err.to_string())),
+                            },
+                        }
+                    },
+                )}.into_any(),
+                Mode::Move => element!{InputLine(
+                    label: "Move to: ",
+                    text: prompt_input.to_string(),
+                    cursor: prompt_cursor.get(),
+                    message: Some("Enter to move, Esc to cancel".to_string()),
+                    message_color: Color::DarkGrey,
+                )}.into_any(),
+                // The search bar would pick up the keys meant for the other prompts,
+                // so it's only shown once they are closed
+                Mode::Rename | Mode::ConfirmDelete | Mode::Conflict | Mode::Trash | Mode::Template => element!{InputLine(
+                    label: "Search: ",
+                    text: query.to_string(),
+                    cursor: query.read().chars().count(),
+                    message: match mode.get() {
+                        Mode::Rename => Some("Enter to rename, Esc to cancel".to_string()),
+                        Mode::Trash => Some("Enter to restore, Esc to go back".to_string()),
+                        Mode::Template => Some("Enter to pick, Esc to cancel".to_string()),
+                        _ => None,
+                    },
+                    message_color: Color::DarkGrey,
+                )}.into_any(),
+                Mode::Search => element!{SearchBar(
+                    query: query.to_string(),
+                    favorites_only: favorites_only.get(),
+                    error: query_error,
+                    on_change: move |new_query| query.set(new_query),
+                )}.into_any(),
+            })
+
+            #(match (mode.get(), selected) {
+                (Mode::ConfirmDelete, Some(entry)) => element!{ConfirmDialog(
+                    term_width: props.term_width,
+                    term_height: props.term_height,
+                    message: format!("Delete \"{}\"?", entry.title),
+                    detail: "It will be moved to the trash. y to delete, n to cancel",
+                )}.into_any(),
+                (Mode::Conflict, _) => element!{ConfirmDialog(
+                    term_width: props.term_width,
+                    term_height: props.term_height,
+                    message: format!(
+                        "\"{}\" changed on disk since it was read",
+                        conflict.read().as_ref().map_or(String::new(), |entry| entry.title.clone()),
+                    ),
+                    detail: "r to reload it, o to overwrite it, Esc to cancel",
+                )}.into_any(),
+                (Mode::Template, _) => element!{TemplatePicker(
+                    term_width: props.term_width,
+                    term_height: props.term_height,
+                    names: templates.read().iter().map(|template| template.name.clone()).collect::<Vec<String>>(),
+                    cursor: template_cursor.get(),
+                )}.into_any(),
+                _ => element!{View}.into_any(),
+            })
+        }
+    }
+}
+
+/// What the keyboard is currently being used for on the main page
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Search,
+    Tag,
+    /// Editing the file stem of the selected entry, inline in the note list
+    Rename,
+    /// Picking a directory to move the selected entry into
+    Move,
+    ConfirmDelete,
+    /// Asking what to do about an entry which changed on disk before it could be written
+    Conflict,
+    /// Browsing deleted entries, in place of the note list
+    Trash,
+    /// Picking what a new entry starts out as
+    Template,
+}
+
+#[derive(Props, Default)]
+struct TrashListProps {
+    width: u16,
+    height: u16,
+    entries: Vec<TrashedEntry>,
+    cursor: usize,
+}
+
+/// Deleted entries, most recently deleted first
+#[component]
+fn TrashList(props: &TrashListProps) -> impl Into<AnyElement<'static>> {
+    // Leaves room for the header
+    let visible_rows = props.height.saturating_sub(1) as usize;
+    let scroll = (props.cursor + 1).saturating_sub(visible_rows);
+
+    element! {
+        View(
+            border_style: BorderStyle::Round,
+            border_color: Color::White,
+            flex_direction: FlexDirection::Column,
+            width: props.width,
+            padding_left: 1,
+            padding_right: 1,
+        ) {
+            Text(content: "Trash", weight: Weight::Bold)
+
+            #(match props.entries.is_empty() {
+                true => vec![element! {
+                    Text(content: "The trash is empty", color: Color::DarkGrey)
+                }.into_any()],
+                false => props.entries.iter().enumerate().skip(scroll).take(visible_rows).map(|(index, entry)| {
+                    let deleted_at = Local
+                        .timestamp_opt(entry.deleted_at as i64, 0)
+                        .single()
+                        .map_or(String::new(), |time| time.format("%Y-%m-%d %H:%M ").to_string());
+
+                    let original_path = entry.original_path.display().to_string();
+
+                    element! {
+                        View(height: 1) {
+                            Text(content: deleted_at, color: Color::Blue, wrap: TextWrap::NoWrap)
+                            Text(
+                                content: entry.title(),
+                                color: if index == props.cursor { Color::Red } else { Color::White },
+                                wrap: TextWrap::NoWrap,
+                            )
+                            Text(content: format!(" {}", original_path), color: Color::DarkGrey, wrap: TextWrap::NoWrap)
+                        }
+                    }.into_any()
+                }).collect::<Vec<AnyElement<'static>>>(),
+            })
+        }
+    }
+}
+
+/// A short message about the outcome of an action, shown in the status bar
+#[derive(Clone, Debug, Default)]
+struct Toast {
+    message: String,
+    is_error: bool,
+}
+
+impl Toast {
+    fn info(message: String) -> Toast {
+        Toast {
+            message,
+            is_error: false,
+        }
+    }
+
+    fn error(message: String) -> Toast {
+        Toast {
+            message,
+            is_error: true,
+        }
+    }
+}
+
+#[derive(Props, Default)]
+struct ConfirmDialogProps {
+    term_width: u16,
+    term_height: u16,
+    message: String,
+    detail: String,
+}
+
+/// Asks for confirmation in a box drawn over the middle of the screen,
+/// the keys are handled by whoever shows it
+#[component]
+fn ConfirmDialog(props: &ConfirmDialogProps) -> impl Into<AnyElement<'static>> {
+    let width = min(
+        max(props.message.chars().count(), props.detail.chars().count()) as u16 + 4,
+        props.term_width,
+    );
+
+    element! {
+        View(
+            position: Position::Absolute,
+            top: props.term_height.saturating_sub(4) / 2,
+            left: props.term_width.saturating_sub(width) / 2,
+            width,
+            height: 4,
+            border_style: BorderStyle::Round,
+            border_color: Color::Red,
+            background_color: Color::Reset,
+            flex_direction: FlexDirection::Column,
+            padding_left: 1,
+            padding_right: 1,
+        ) {
+            Text(content: props.message.clone(), weight: Weight::Bold, wrap: TextWrap::NoWrap)
+            Text(content: props.detail.clone(), color: Color::DarkGrey, wrap: TextWrap::NoWrap)
+        }
+    }
+}
+
+#[derive(Props, Default)]
+struct TemplatePickerProps {
     term_width: u16,
     term_height: u16,
+    names: Vec<String>,
+    /// Position of the cursor, where 0 is the blank note listed before the templates
+    cursor: usize,
 }
 
+/// Lists the templates a new note can start out as, in a box drawn over the
+/// middle of the screen. The keys are handled by whoever shows it
 #[component]
-fn MainPage(mut hooks: Hooks, props: &MainPageProps) -> impl Into<AnyElement<'static>> {
-    // Manually adjusting width of the containers isn't a great idea, but I can't
-    // seem to get flexboxes to play nicely at the moment
-    let note_list_width = match props.show_note_content {
-        true => props.term_width / 2,
-        false => props.term_width - 2,
-    };
+fn TemplatePicker(props: &TemplatePickerProps) -> impl Into<AnyElement<'static>> {
+    let names = iter::once("Blank note")
+        .chain(props.names.iter().map(String::as_str))
+        .collect::<Vec<&str>>();
 
-    let mut selected_entry: State<Option<usize>> = hooks.use_state(|| None);
+    let width = min(
+        max(
+            names
+                .iter()
+                .map(|name| name.chars().count())
+                .max()
+                .unwrap_or(0)
+                + 4,
+            "New note from".len(),
+        ) as u16
+            + 4,
+        props.term_width,
+    );
+    let height = min(names.len() as u16 + 3, props.term_height);
 
-    let entry_count = props.database.entries.len().clone();
+    element! {
+        View(
+            position: Position::Absolute,
+            top: props.term_height.saturating_sub(height) / 2,
+            left: props.term_width.saturating_sub(width) / 2,
+            width,
+            height,
+            border_style: BorderStyle::Round,
+            border_color: Color::Blue,
+            background_color: Color::Reset,
+            flex_direction: FlexDirection::Column,
+            padding_left: 1,
+            padding_right: 1,
+        ) {
+            Text(content: "New note from", weight: Weight::Bold, wrap: TextWrap::NoWrap)
+            #(names.into_iter().enumerate().map(|(index, name)| {
+                let is_selected = index == props.cursor;
 
-    if entry_count > 0 && selected_entry.get() == None {
-        selected_entry.set(Some(0));
+                element! {
+                    Text(
+                        content: format!("{} {}", if is_selected { "›" } else { " " }, name),
+                        color: if is_selected { Color::Red } else { Color::White },
+                        wrap: TextWrap::NoWrap,
+                    )
+                }
+            }))
+        }
     }
+}
 
-    if entry_count == 0 {
-        selected_entry.set(None);
-    }
+/// Width of the tag panel, including its border
+const TAG_PANEL_WIDTH: u16 = 26;
 
-    hooks.use_terminal_events({
-        move |event| match event {
-            TerminalEvent::Key(KeyEvent { code, kind, .. }) if kind != KeyEventKind::Release => {
-                match code {
-                    KeyCode::Up => match selected_entry.get() {
-                        Some(index) => {
-                            selected_entry.set(Some(max(index as i32 - 1, 0 as i32) as usize))
-                        }
-                        None => {}
-                    },
-                    KeyCode::Down => match selected_entry.get() {
-                        Some(index) => selected_entry
-                            .set(Some(min(index as i32 + 1, entry_count as i32 - 1) as usize)),
-                        None => {}
-                    },
-                    _ => {}
-                }
-            }
-            _ => {}
-        }
-    });
+#[derive(Props, Default)]
+struct TagPanelProps {
+    width: u16,
+    height: u16,
+    nodes: Vec<TagNode>,
+    /// Position of the cursor in `nodes`, only set while the panel has focus
+    cursor: Option<usize>,
+    /// Tags the note list is filtered by
+    selected: Vec<String>,
+    /// Whether notes need any of the selected tags, rather than all of them
+    match_any: bool,
+}
+
+/// Every tag in use as a tree, selecting tags filters the note list
+#[component]
+fn TagPanel(props: &TagPanelProps) -> impl Into<AnyElement<'static>> {
+    // Leaves room for the header
+    let visible_rows = props.height.saturating_sub(1) as usize;
+
+    // Keep the cursor in view by scrolling just far enough
+    let scroll = props
+        .cursor
+        .map_or(0, |cursor| (cursor + 1).saturating_sub(visible_rows));
+
+    // Leaves room for the border, the padding, and a space before the count
+    let text_width = props.width.saturating_sub(5) as usize;
+
+    let header = match props.match_any {
+        true => "match any",
+        false => "match all",
+    };
 
     element! {
         View(
-            display: Display::Flex,
-            height: props.term_height,
-            width: props.term_width,
+            border_style: BorderStyle::Round,
+            border_color: match props.cursor {
+                Some(_) => Color::Cyan,
+                None => Color::White,
+            },
             flex_direction: FlexDirection::Column,
-            justify_content: JustifyContent::SpaceBetween,
+            width: props.width,
             padding_left: 1,
             padding_right: 1,
         ) {
-            StatusBar()
+            View(height: 1, justify_content: JustifyContent::SpaceBetween) {
+                Text(content: "Tags", weight: Weight::Bold)
+                Text(content: header, color: Color::DarkGrey)
+            }
 
-            View(
-                display: Display::Flex,
-                flex_direction: FlexDirection::Row,
-                height: 100pct,
-                height: props.term_height,
-                width: props.term_width - 2,
-                ) {
-                NoteList(width: note_list_width, entries: props.database.entries.clone(), selected_entry: selected_entry.read().clone())
+            #(props.nodes.iter().enumerate().skip(scroll).take(visible_rows).map(|(index, node)| {
+                let is_selected = props.selected.contains(&node.path);
+                let count = node.count.to_string();
 
-                // Hide the content of a note if the terminal is smaller than
-                // or equal to the react width set through the config
-                #(match props.show_note_content {
-                        true => element!{NoteContent()}.into_any(),
-                        false => element!{View}.into_any(),
-                })
-            }
+                let label = format!(
+                    "{}{} {}",
+                    "  ".repeat(node.depth),
+                    if is_selected { "●" } else { "○" },
+                    node.name,
+                );
+                let label = label
+                    .chars()
+                    .take(text_width.saturating_sub(count.len()))
+                    .collect::<String>();
 
-            SearchBar()
+                element! {
+                    View(height: 1, justify_content: JustifyContent::SpaceBetween) {
+                        Text(
+                            content: label,
+                            color: match (props.cursor == Some(index), is_selected) {
+                                (true, _) => Color::Red,
+                                (false, true) => Color::Yellow,
+                                (false, false) => Color::White,
+                            },
+                            wrap: TextWrap::NoWrap,
+                        )
+                        Text(content: count, color: Color::DarkGrey)
+                    }
+                }
+            }))
         }
     }
 }
@@ -210,8 +1652,15 @@ fn ResizeTermPage(props: &ResizeTermPageProps) -> impl Into<AnyElement<'static>>
     }
 }
 
+#[derive(Props, Default)]
+struct StatusBarProps {
+    /// Shown in place of the key hints
+    toast: Option<Toast>,
+    sort_order: SortOrder,
+}
+
 #[component]
-fn StatusBar() -> impl Into<AnyElement<'static>> {
+fn StatusBar(props: &StatusBarProps) -> impl Into<AnyElement<'static>> {
     element! {
         View(
             height: 1,
@@ -229,17 +1678,31 @@ fn StatusBar() -> impl Into<AnyElement<'static>> {
             //
             // TODO: In the future, set the width equal to the amount of characters in the
             // content field of the Text element in the 3rd, div. Eg:
-            // "entry count: 144"   - width = 16
-            // "entry count: 1092"  - width = 17
+            // "sort: title"        - width = 11
+            // "sort: modified ↓"   - width = 16
             View(width: 19) {
                 Text(content: "Thoughts", weight: Weight::Bold, align: TextAlign::Left)
             }
-            View() {
-                Text(content: "Esc ", weight: Weight::Bold, align: TextAlign::Center, color: Color::DarkGrey, wrap: TextWrap::NoWrap)
-                Text(content: "to exit", align: TextAlign::Center, color: Color::DarkGrey)
-            }
+            #(match &props.toast {
+                Some(toast) => element! {
+                    View() {
+                        Text(
+                            content: toast.message.clone(),
+                            align: TextAlign::Center,
+                            color: if toast.is_error { Color::Red } else { Color::Green },
+                            wrap: TextWrap::NoWrap,
+                        )
+                    }
+                }.into_any(),
+                None => element! {
+                    View() {
+                        Text(content: "Esc ", weight: Weight::Bold, align: TextAlign::Center, color: Color::DarkGrey, wrap: TextWrap::NoWrap)
+                        Text(content: "to exit", align: TextAlign::Center, color: Color::DarkGrey)
+                    }
+                }.into_any(),
+            })
             View(width: 19, justify_content: JustifyContent::End) {
-                Text(content: "entry count: 144", align: TextAlign::Right, color: Color::DarkGrey)
+                Text(content: format!("sort: {}", props.sort_order.label()), align: TextAlign::Right, color: Color::DarkGrey, wrap: TextWrap::NoWrap)
             }
         }
     }
@@ -248,54 +1711,240 @@ fn StatusBar() -> impl Into<AnyElement<'static>> {
 #[derive(Props, Default)]
 struct NoteListProps {
     width: u16,
-    entries: Vec<Entry>,
+    /// Amount of rows which fit in the list
+    height: usize,
+    /// Entries in view, along with how they matched the search
+    rows: Vec<(Entry, SearchResult)>,
+    /// Position of the first row in view amongst every result
+    first_row: usize,
+    /// Amount of results, including the ones out of view
+    total: usize,
     selected_entry: Option<usize>,
+    timestamp_style: TimestampStyle,
+    list_timestamp: ListTimestamp,
+    /// Title being typed in place of the selected entry, and the position of the cursor in it
+    rename: Option<(String, usize)>,
 }
 
 #[component]
 fn NoteList(props: &NoteListProps) -> impl Into<AnyElement<'static>> {
+    // The scrollbar only shows up once there is something to scroll to
+    let has_scrollbar = props.total > props.height;
+    let row_width = match has_scrollbar {
+        true => props.width.saturating_sub(1),
+        false => props.width,
+    };
+
+    // The thumb is as big, and as far down, as the part of the list in view
+    let thumb_size = max(props.height * props.height / max(props.total, 1), 1).min(props.height);
+    let thumb_start = props.first_row * (props.height - thumb_size)
+        / max(props.total.saturating_sub(props.height), 1);
+
     element! {
         View(
             border_style: BorderStyle::Round,
             border_color: Color::White,
             flex_grow: 1.0,
             display: Display::Flex,
-            flex_direction: FlexDirection::Column,
+            flex_direction: FlexDirection::Row,
             max_width: props.width,
             // min_width: props.width,
         ) {
-            #(props.entries.clone().into_iter().enumerate().map(|(index, entry)| element!{
-                NoteListEntry(title: entry.title, width: props.width, is_favorite: entry.favorite, is_selected: Some(index) == props.selected_entry)
+            View(
+                width: props.width.saturating_sub(2 + has_scrollbar as u16),
+                flex_direction: FlexDirection::Column,
+            ) {
+                #(props.rows.iter().enumerate().map(|(row, (entry, result))| {
+                    let is_selected = Some(props.first_row + row) == props.selected_entry;
+
+                    if let (true, Some((title, cursor))) = (is_selected, &props.rename) {
+                        return element!{
+                            View(width: row_width, padding_left: 1) {
+                                InputLine(
+                                    label: "Rename: ",
+                                    text: title.clone(),
+                                    cursor: *cursor,
+                                    inline: true,
+                                )
+                            }
+                        }.into_any();
+                    }
+
+                    element!{
+                        NoteListEntry(
+                            title: entry.title.clone(),
+                            title_matches: result.title_matches.clone(),
+                            width: row_width,
+                            is_favorite: entry.favorite,
+                            timestamp: match props.list_timestamp {
+                                ListTimestamp::Created => entry.created_at,
+                                ListTimestamp::Modified => entry.modified_at,
+                            },
+                            timestamp_style: props.timestamp_style.clone(),
+                            tags: entry.tags.clone(),
+                            is_selected,
+                        )
+                    }.into_any()
+                }))
+            }
+
+            #(has_scrollbar.then(|| element! {
+                View(width: 1, flex_shrink: 0.0, flex_direction: FlexDirection::Column) {
+                    #((0..props.height).map(|row| {
+                        match (thumb_start..thumb_start + thumb_size).contains(&row) {
+                            true => element!{Text(content: "┃", color: Color::White)},
+                            false => element!{Text(content: "│", color: Color::DarkGrey)},
+                        }
+                    }))
+                }
             }))
         }
     }
 }
 
+/// How the times in the note list are written, see the matching options in `Config`
+#[derive(Clone, Default)]
+struct TimestampStyle {
+    date_format: String,
+    clock_24h: bool,
+    relative: bool,
+}
+
+impl From<&Config> for TimestampStyle {
+    fn from(config: &Config) -> TimestampStyle {
+        TimestampStyle {
+            date_format: config.date_format.clone(),
+            clock_24h: config.clock_24h,
+            relative: config.relative_time,
+        }
+    }
+}
+
+impl TimestampStyle {
+    /// Formats `timestamp` as a date and a time, both followed by a space
+    /// unless they're left empty, eg: `3h ago ` and nothing for the time
+    fn format(&self, timestamp: u64, now: DateTime<Local>) -> (String, String) {
+        let Some(time) = Local.timestamp_opt(timestamp as i64, 0).single() else {
+            return (String::new(), String::new());
+        };
+
+        let clock = match self.clock_24h {
+            true => time.format("%H:%M ").to_string(),
+            false => time.format("%I:%M%P ").to_string(),
+        };
+
+        if self.relative {
+            let elapsed = now.signed_duration_since(time);
+            let days = (now.date_naive() - time.date_naive()).num_days();
+
+            let relative = match elapsed {
+                _ if elapsed.num_minutes() < 1 => Some(("just now ".to_string(), String::new())),
+                _ if elapsed.num_hours() < 1 => {
+                    Some((format!("{}m ago ", elapsed.num_minutes()), String::new()))
+                }
+                _ if days == 0 => Some((format!("{}h ago ", elapsed.num_hours()), String::new())),
+                _ if days == 1 => Some(("yesterday ".to_string(), clock.clone())),
+                _ if days < 7 => Some((format!("{}d ago ", days), String::new())),
+                _ => None,
+            };
+
+            // Times in the future or far in the past are shown as they are
+            if let Some(relative) = relative.filter(|_| elapsed.num_seconds() >= 0) {
+                return relative;
+            }
+        }
+
+        (format!("{} ", time.format(&self.date_format)), clock)
+    }
+}
+
 #[derive(Props, Default)]
 struct NoteListEntryProps {
     width: u16,
     is_favorite: bool,
-    modified_at: u64,
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+    timestamp_style: TimestampStyle,
     title: String,
+    /// Byte ranges of `title` to highlight
+    title_matches: Vec<Range<usize>>,
+    /// Shown after the title, as far as they fit
+    tags: Vec<String>,
     is_selected: bool,
 }
 
 #[component]
 fn NoteListEntry(props: &NoteListEntryProps) -> impl Into<AnyElement<'static>> {
-    // Use to calculate where to truncate title
-    let width_remaining = props.width - 25;
-    let did_truncate = width_remaining < props.title.len() as u16;
+    let (date, time) = props.timestamp_style.format(props.timestamp, Local::now());
+
+    // Use to calculate where to truncate title, leaving room for the favorite
+    // marker, the timestamp, and the borders of the list
+    let width_remaining = props
+        .width
+        .saturating_sub(6 + (date.chars().count() + time.chars().count()) as u16)
+        as usize;
+    let title_length = props.title.chars().count();
+    let did_truncate = width_remaining < title_length;
+
+    // Truncate on a char boundary, as titles are allowed to contain multi-byte characters
+    let title_end = props
+        .title
+        .char_indices()
+        .nth(width_remaining)
+        .map_or(props.title.len(), |(index, _)| index);
+
+    let title_color = match props.is_selected {
+        true => Color::Red,
+        false => Color::White,
+    };
+
+    // Split the visible part of the title into alternating plain and highlighted segments
+    let mut title_matches = props.title_matches.clone();
+    title_matches.sort_by_key(|range| range.start);
 
-    let mut title = props.title.clone();
-    title.truncate(width_remaining.into());
+    let mut segments: Vec<(&str, bool)> = vec![];
+    let mut segment_start = 0;
+
+    for range in &title_matches {
+        let start = range.start.min(title_end);
+        let end = range.end.min(title_end);
+
+        if start < segment_start || start == end {
+            continue;
+        }
+
+        segments.push((&props.title[segment_start..start], false));
+        segments.push((&props.title[start..end], true));
+        segment_start = end;
+    }
+
+    segments.push((&props.title[segment_start..title_end], false));
+
+    let tags_width = width_remaining.saturating_sub(title_length);
+    let tags = props
+        .tags
+        .iter()
+        .map(|tag| format!(" #{}", tag))
+        .collect::<String>()
+        .chars()
+        .take(tags_width)
+        .collect::<String>();
 
     element! {
         View(width: props.width) {
             Text(content: if props.is_favorite {" ★ "} else {" ☆ "})
-            Text(content: "06-06-2023 ", color: Color::Blue)
-            Text(content: "11:40am ", color: Color::Green)
-            Text(content: title, color: if props.is_selected { Color::Red } else { Color::White })
+            Text(content: date, color: Color::Blue, wrap: TextWrap::NoWrap)
+            Text(content: time, color: Color::Green, wrap: TextWrap::NoWrap)
+            #(segments.into_iter().filter(|(segment, _)| !segment.is_empty()).map(|(segment, is_match)| element! {
+                Text(
+                    content: segment,
+                    color: if is_match { Color::Yellow } else { title_color },
+                    weight: if is_match { Weight::Bold } else { Weight::Normal },
+                    wrap: TextWrap::NoWrap,
+                )
+            }))
             Text(content: if did_truncate {"…"} else {""})
+            Text(content: tags, color: Color::DarkGrey, wrap: TextWrap::NoWrap)
         }
     }
 }
@@ -303,150 +1952,426 @@ fn NoteListEntry(props: &NoteListEntryProps) -> impl Into<AnyElement<'static>> {
 #[derive(Props, Default)]
 struct NoteContentProps {
     width: u16,
+    height: u16,
+    entry: Option<Entry>,
+    /// Whether the entry is a daily note, see `DailyNotes`
+    is_daily: bool,
+    /// Titles of the daily notes before and after this one
+    previous_day: Option<String>,
+    next_day: Option<String>,
 }
 
 #[component]
-fn NoteContent(props: &NoteContentProps) -> impl Into<AnyElement<'static>> {
+fn NoteContent(mut hooks: Hooks, props: &NoteContentProps) -> impl Into<AnyElement<'static>> {
+    // Scrolling is independent of the note list, but starts over whenever
+    // a different entry is selected
+    let mut scroll = hooks.use_state(|| 0);
+    let mut scrolled_path: State<Option<PathBuf>> = hooks.use_state(|| None);
+    let mut page_height = hooks.use_state(|| 0);
+
+    let path = props.entry.as_ref().map(|entry| entry.path.clone());
+    if *scrolled_path.read() != path {
+        scrolled_path.set(path);
+        scroll.set(0);
+    }
+
+    let page = props.height as usize;
+    if page_height.get() != page {
+        page_height.set(page);
+    }
+
+    // Leaves room for the borders and padding of the pane
+    let text_width = props.width.saturating_sub(4) as usize;
+    let mut lines = vec![];
+
+    if let Some(entry) = &props.entry {
+        if props.is_daily {
+            lines.push(daily_line(&props.previous_day, &props.next_day));
+            lines.push(Line::default());
+        }
+
+        if !entry.tags.is_empty() {
+            lines.push(tags_line(&entry.tags));
+            lines.push(Line::default());
+        }
+
+        lines.extend(markdown::render(&entry.content, text_width));
+    }
+
+    let max_scroll = lines.len().saturating_sub(page);
+    if scroll.get() > max_scroll {
+        scroll.set(max_scroll);
+    }
+
+    hooks.use_terminal_events({
+        move |event| match event {
+            TerminalEvent::Key(KeyEvent {
+                code,
+                kind,
+                modifiers,
+                ..
+            }) if kind != KeyEventKind::Release => {
+                match code {
+                    // Without alt, the arrow and paging keys move through the note list.
+                    // Terminals tend to keep shift with the paging keys for their own scrollback
+                    KeyCode::Up if modifiers.contains(KeyModifiers::ALT) => {
+                        scroll.set(scroll.get().saturating_sub(1))
+                    }
+                    KeyCode::Down if modifiers.contains(KeyModifiers::ALT) => {
+                        scroll.set(scroll.get() + 1)
+                    }
+                    KeyCode::PageUp if modifiers.contains(KeyModifiers::ALT) => {
+                        scroll.set(scroll.get().saturating_sub(page_height.get()))
+                    }
+                    KeyCode::PageDown if modifiers.contains(KeyModifiers::ALT) => {
+                        scroll.set(scroll.get() + page_height.get())
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    });
+
     element! {
         View(
             border_style: BorderStyle::Round,
             border_color: Color::White,
             flex_grow: 1.0,
-            // max_width: props.width,
+            flex_direction: FlexDirection::Column,
+            max_width: props.width,
+            padding_left: 1,
+            padding_right: 1,
             // min_width: props.width,
-        )
+        ) {
+            #(lines.into_iter().skip(scroll.get()).take(page).map(|line| element! {
+                View(height: 1, background_color: line.background) {
+                    #(line.spans.into_iter().map(|span| element! {
+                        View(background_color: span.style.background) {
+                            Text(
+                                content: span.text,
+                                color: span.style.color,
+                                weight: span.style.weight,
+                                decoration: match span.style.underline {
+                                    true => TextDecoration::Underline,
+                                    false => TextDecoration::None,
+                                },
+                                wrap: TextWrap::NoWrap,
+                            )
+                        }
+                    }))
+                }
+            }))
+        }
     }
 }
 
-#[component]
-fn SearchBar(mut hooks: Hooks) -> impl Into<AnyElement<'static>> {
-    // TODO: Allow for text to scroll if it extends pass the bounds of what
-    // can be displayed to the user
-    let mut query = hooks.use_state(|| String::new());
-    let mut cursor_position = hooks.use_state(|| 0);
-    let query_cloned = query.to_string();
-    // I unironically had to whiteboard the following logic, I feel so dumb.
-    //
-    // Brazinski's algorithm:
-    // Assuming a stateful, append-only stream of alpha-numerical userinput, and a stateful
-    // "cursor", how could one infer the correct position of the appended text.
-    //
-    // The solution is to track both the previous buffer, and the current buffer.
-    //
-    // Eg:
-    //
-    // Old State: ["h", "e", "l", "o"]
-    // New State: ["h", "e", "l", "o", "l"]
-    // Cursor Position: 3
-    //
-    // Length of Old State = 4
-    // Length of New State = 5
-    // Length difference = 5 - 4 = 1
-    //
-    // The length of the difference is 1, meaning we pop the most recently appended character,
-    // and insert it at the position of the cursor.
-
-    // // there is some text after the cursor if the position of the cursor is less
-    // than the total length of the query
-    let after_cursor = if cursor_position < query_cloned.len() {
-        &query_cloned[cursor_position.get() + 1..query_cloned.len()]
-    } else {
-        ""
+/// Links to the daily notes either side of a daily note, shown above its content
+fn daily_line(previous_day: &Option<String>, next_day: &Option<String>) -> Line {
+    let style = Style {
+        color: Some(Color::DarkGrey),
+        ..Default::default()
     };
 
-    // there is some text before the cursor if the position of the cursor is greater than 0
-    let before_cursor = if cursor_position.get() > 0 {
-        &query_cloned[..cursor_position.get()]
-    } else {
-        ""
-    };
+    let previous = previous_day
+        .as_ref()
+        .map_or(String::new(), |title| format!("Alt+, ← {}", title));
+    let next = next_day
+        .as_ref()
+        .map_or(String::new(), |title| format!("{} → Alt+.", title));
 
-    // there is some text under the cursor at the position of the cursor
-    let during_cursor = match &query_cloned.get(cursor_position.get()..=cursor_position.get()) {
-        Some(char) => *char,
-        None => " ",
+    Line {
+        spans: vec![Span {
+            text: match (previous.is_empty(), next.is_empty()) {
+                (true, true) => "No other daily notes yet".to_string(),
+                (false, false) => format!("{}   {}", previous, next),
+                _ => format!("{}{}", previous, next),
+            },
+            style,
+        }],
+        background: None,
+    }
+}
+
+/// The tags of an entry, shown above its content
+fn tags_line(tags: &[String]) -> Line {
+    let style = Style {
+        color: Some(Color::DarkGrey),
+        ..Default::default()
     };
 
+    Line {
+        spans: tags
+            .iter()
+            .map(|tag| Span {
+                text: format!("#{} ", tag),
+                style,
+            })
+            .collect(),
+        background: None,
+    }
+}
+
+#[derive(Props, Default)]
+struct SearchBarProps {
+    /// Query to start out with, the bar keeps track of it from then on
+    query: String,
+    /// Only favorites are listed, regardless of the query
+    favorites_only: bool,
+    /// Shown next to the query when it can't be parsed
+    error: Option<String>,
+    on_change: Handler<'static, String>,
+}
+
+#[component]
+fn SearchBar(mut hooks: Hooks, props: &mut SearchBarProps) -> impl Into<AnyElement<'static>> {
+    let mut query = hooks.use_state(|| props.query.clone());
+    // Counted in chars rather than bytes, so multi-byte input can't split the query
+    let mut cursor_position = hooks.use_state(|| props.query.chars().count());
+
+    // Only the handler from the first render is kept, as the event handler
+    // below is only created once
+    let mut on_change = props.on_change.take();
+
+    hooks.use_terminal_events({
+        move |event| match event {
+            TerminalEvent::Key(KeyEvent {
+                code,
+                kind,
+                modifiers,
+                ..
+            }) if kind != KeyEventKind::Release => {
+                let mut chars = query.read().chars().collect::<Vec<char>>();
+                let mut cursor = cursor_position.get();
+
+                if edit_line(&mut chars, &mut cursor, code, modifiers) {
+                    query.set(chars.into_iter().collect());
+                    on_change(query.to_string());
+                }
+
+                cursor_position.set(cursor);
+            }
+            _ => {}
+        }
+    });
+
+    element! {
+        InputLine(
+            label: match props.favorites_only {
+                true => "Search ★: ",
+                false => "Search: ",
+            },
+            text: query.to_string(),
+            cursor: cursor_position.get(),
+            message: props.error.clone(),
+            message_color: Color::Red,
+        )
+    }
+}
+
+#[derive(Props, Default)]
+struct TagBarProps {
+    /// Every tag in use, offered as completions, most used first
+    tags: Vec<String>,
+    /// Tags the selected entry already has
+    entry_tags: Vec<String>,
+    error: Option<String>,
+    /// Called with the input when Enter is pressed
+    on_submit: Handler<'static, String>,
+}
+
+/// Prompt for tags to add to the selected entry, or to remove from it when
+/// prefixed with `-`. Tab completes the tag being typed.
+#[component]
+fn TagBar(mut hooks: Hooks, props: &mut TagBarProps) -> impl Into<AnyElement<'static>> {
+    let mut input = hooks.use_state(String::new);
+    let mut cursor_position = hooks.use_state(|| 0);
+
+    let mut on_submit = props.on_submit.take();
+    let completion = complete_tag(&input.read(), &props.tags, &props.entry_tags);
+
     hooks.use_terminal_events({
+        // The prompt is closed before the tags could change, so these can be captured
+        let tags = props.tags.clone();
+        let entry_tags = props.entry_tags.clone();
+
         move |event| match event {
-            TerminalEvent::Key(KeyEvent { code, kind, .. }) if kind != KeyEventKind::Release => {
+            TerminalEvent::Key(KeyEvent {
+                code,
+                kind,
+                modifiers,
+                ..
+            }) if kind != KeyEventKind::Release => {
+                let mut chars = input.read().chars().collect::<Vec<char>>();
+                let mut cursor = cursor_position.get();
+
                 match code {
-                    KeyCode::Left => {
-                        if cursor_position.get() > 0 {
-                            cursor_position.set(cursor_position - 1);
+                    KeyCode::Enter => on_submit(input.to_string()),
+                    KeyCode::Tab => {
+                        let completion = complete_tag(&input.to_string(), &tags, &entry_tags);
+
+                        if let Some(completion) = completion {
+                            cursor_position.set(completion.chars().count());
+                            input.set(completion);
                         }
                     }
-                    KeyCode::Right => {
-                        if cursor_position.get() < query.to_string().len() {
-                            cursor_position.set(cursor_position + 1);
+                    _ => {
+                        if edit_line(&mut chars, &mut cursor, code, modifiers) {
+                            input.set(chars.into_iter().collect());
                         }
+
+                        cursor_position.set(cursor);
                     }
-                    _ => {}
                 }
             }
             _ => {}
         }
     });
 
+    let current_tags = props
+        .entry_tags
+        .iter()
+        .map(|tag| format!("#{}", tag))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let (message, message_color) = match &props.error {
+        Some(error) => (error.clone(), Color::Red),
+        None => (current_tags, Color::DarkGrey),
+    };
+
+    element! {
+        InputLine(
+            label: "Tag: ",
+            text: input.to_string(),
+            cursor: cursor_position.get(),
+            completion: completion.map(|completion| {
+                completion.chars().skip(input.read().chars().count()).collect::<String>()
+            }),
+            message,
+            message_color,
+        )
+    }
+}
+
+/// Completes the tag being typed at the end of `input`, eg: `work, pro` becomes
+/// `work, project`. Tags being removed are completed from the ones the entry has.
+fn complete_tag(input: &str, tags: &[String], entry_tags: &[String]) -> Option<String> {
+    let word_start = input.rfind([',', ' ']).map_or(0, |index| index + 1);
+    let word = &input[word_start..];
+
+    let (partial, candidates, is_removal) = match word.strip_prefix('-') {
+        Some(partial) => (partial, entry_tags, true),
+        None => (word, tags, false),
+    };
+
+    let partial = partial.trim_start_matches('#');
+    if partial.is_empty() {
+        return None;
+    }
+
+    let tag = candidates.iter().find(|tag| {
+        tag.len() > partial.len()
+            && thoughts::tags::fold(tag).starts_with(&thoughts::tags::fold(partial))
+            && (is_removal || !thoughts::tags::contains(entry_tags, tag))
+    })?;
+
+    Some(format!("{}{}", &input[..input.len() - partial.len()], tag))
+}
+
+/// Applies a key press to a single line of text being edited,
+/// returns whether the text itself changed
+fn edit_line(
+    chars: &mut Vec<char>,
+    cursor: &mut usize,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> bool {
+    // Chords are reserved for actions, so they shouldn't end up in the text
+    let is_chord = modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+    match code {
+        KeyCode::Char(char) if !is_chord => {
+            chars.insert(*cursor, char);
+            *cursor += 1;
+            true
+        }
+        KeyCode::Backspace if *cursor > 0 => {
+            chars.remove(*cursor - 1);
+            *cursor -= 1;
+            true
+        }
+        KeyCode::Left if *cursor > 0 => {
+            *cursor -= 1;
+            false
+        }
+        KeyCode::Right if *cursor < chars.len() => {
+            *cursor += 1;
+            false
+        }
+        _ => false,
+    }
+}
+
+#[derive(Props, Default)]
+struct InputLineProps {
+    label: String,
+    text: String,
+    /// Position of the cursor in chars
+    cursor: usize,
+    /// Greyed out text after the cursor, which Tab would fill in
+    completion: Option<String>,
+    /// Shown on the right hand side
+    message: Option<String>,
+    message_color: Option<Color>,
+    /// Drawn without a border, so that it fits in a single row
+    inline: bool,
+}
+
+#[component]
+fn InputLine(props: &InputLineProps) -> impl Into<AnyElement<'static>> {
+    // TODO: Allow for text to scroll if it extends pass the bounds of what
+    // can be displayed to the user
+    let chars = props.text.chars().collect::<Vec<char>>();
+
+    // there is some text before the cursor if the position of the cursor is greater than 0
+    let before_cursor = chars[..props.cursor].iter().collect::<String>();
+
+    // The completion starts under the cursor when it's at the end of the text
+    let mut completion = props.completion.clone().unwrap_or_default();
+
+    // there is some text under the cursor at the position of the cursor
+    let during_cursor = match chars.get(props.cursor) {
+        Some(char) => char.to_string(),
+        None if !completion.is_empty() => completion.remove(0).to_string(),
+        None => " ".to_string(),
+    };
+
+    // there is some text after the cursor if the position of the cursor is less
+    // than the total length of the text
+    let after_cursor = chars
+        .get(props.cursor + 1..)
+        .unwrap_or_default()
+        .iter()
+        .collect::<String>();
+
     element! {
         View(
-            height: 3,
-            border_style: BorderStyle::Round,
+            height: if props.inline { 1 } else { 3 },
+            border_style: if props.inline { BorderStyle::None } else { BorderStyle::Round },
             border_color: Color::White,
         ) {
-            Text(content: "Search: ", wrap: TextWrap::NoWrap)
+            Text(content: props.label.clone(), wrap: TextWrap::NoWrap)
             View() {
-                Text(content: *before_cursor)
+                Text(content: before_cursor)
                 View(background_color: Color::White) {
-                    Text(content: *during_cursor, color: Color::Black)
+                    Text(content: during_cursor, color: Color::Black)
                 }
-                Text(content: *after_cursor)
+                Text(content: after_cursor)
+                Text(content: completion, color: Color::DarkGrey)
             }
-
-            View(width: 0) {
-                TextInput(
-                        has_focus: true,
-                        value: query.to_string(),
-                        on_change: move |new_value: String| {
-                            let mut new_value = new_value.clone();
-                            let mut old_value = query.to_string();
-
-                            // We have to cast these to i32s, as we rely on significant negation
-                            let length_difference = new_value.len() as i32 - old_value.len() as i32;
-
-                            // if length_difference is positive, we've adding characters
-                            if length_difference > 0 {
-                                // This cast is safe, as the difference in length can
-                                // never exceed the length of the array
-                                let start = new_value.len() - length_difference as usize;
-
-                                for (index, char) in new_value.clone()[start..].chars().enumerate() {
-                                    new_value.pop();
-                                    new_value.insert(cursor_position.get() + index, char);
-                                }
-                            }
-
-                            // if length_difference is negative, we've removed characters
-                            if length_difference < 0 {
-                                // if we're pressing backspace at the start of the string, we
-                                // shouldn't do anything
-                                if cursor_position > 0 {
-                                    for _ in 0..length_difference.abs() as usize {
-                                        old_value.remove(cursor_position.get() - 1);
-                                    }
-                                }
-
-                                new_value = old_value;
-                            }
-
-                            // offset the cursor by the amount of chars that were removed or added
-                            let new_cursor_pos = max(cursor_position.get() as i32 + length_difference, 0) as usize;
-                            cursor_position.set(new_cursor_pos);
-
-                            query.set(new_value);
-                        },
-
-                )
+            View(flex_grow: 1.0, justify_content: JustifyContent::End, padding_right: 1) {
+                Text(content: props.message.clone().unwrap_or_default(), color: props.message_color, wrap: TextWrap::NoWrap)
             }
         }
     }
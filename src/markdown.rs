@@ -0,0 +1,551 @@
+use iocraft::prelude::{Color, Weight};
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::mem;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub background: Option<Color>,
+    pub weight: Weight,
+    pub underline: bool,
+}
+
+impl Style {
+    fn color(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            ..Default::default()
+        }
+    }
+
+    /// Layers `other` on top of `self`
+    fn merge(self, other: Style) -> Style {
+        Style {
+            color: other.color.or(self.color),
+            background: other.background.or(self.background),
+            weight: match other.weight {
+                Weight::Normal => self.weight,
+                weight => weight,
+            },
+            underline: self.underline || other.underline,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Line {
+    pub spans: Vec<Span>,
+    /// Fills the entire width of the line, used for code blocks
+    pub background: Option<Color>,
+}
+
+impl Line {
+    fn is_blank(&self) -> bool {
+        self.spans.is_empty() && self.background.is_none()
+    }
+}
+
+/// Renders Markdown into styled lines, none of which are wider than `width` columns
+pub fn render(content: &str, width: usize) -> Vec<Line> {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer {
+        width: width.max(1),
+        ..Default::default()
+    };
+
+    for event in Parser::new_ext(content, options) {
+        renderer.push_event(event);
+    }
+
+    renderer.flush();
+
+    while renderer.lines.last().is_some_and(Line::is_blank) {
+        renderer.lines.pop();
+    }
+
+    renderer.lines
+}
+
+struct List {
+    /// The number of the next item, `None` for bullet lists
+    next_number: Option<u64>,
+    /// Width of the marker of the most recent item, used to indent
+    /// wrapped lines and nested lists
+    marker_width: usize,
+}
+
+#[derive(Default)]
+struct Renderer {
+    width: usize,
+    lines: Vec<Line>,
+    /// Inline content of the block that is currently being built
+    spans: Vec<Span>,
+    /// Styles of the inline elements that we are currently inside of
+    styles: Vec<Style>,
+    lists: Vec<List>,
+    /// Marker of the list item whose first line hasn't been written yet
+    item_marker: Option<Span>,
+    quote_depth: usize,
+    in_code_block: bool,
+    /// Destinations of the links that we are currently inside of
+    link_urls: Vec<String>,
+}
+
+impl Renderer {
+    fn push_event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start_tag(tag),
+            Event::End(tag) => self.end_tag(tag),
+            Event::Text(text) if self.in_code_block => self.push_code_block(&text),
+            Event::Text(text) => self.push_text(&text, Style::default()),
+            Event::Code(code) => self.push_text(
+                &code,
+                Style {
+                    color: Some(Color::Green),
+                    background: Some(Color::DarkGrey),
+                    ..Default::default()
+                },
+            ),
+            Event::Html(html) | Event::InlineHtml(html) => {
+                self.push_text(&html, Style::color(Color::DarkGrey))
+            }
+            Event::SoftBreak => self.push_text(" ", Style::default()),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.lines.push(Line {
+                    spans: vec![Span {
+                        text: "─".repeat(self.width),
+                        style: Style::color(Color::DarkGrey),
+                    }],
+                    background: None,
+                });
+                self.push_blank_line();
+            }
+            Event::TaskListMarker(checked) => {
+                self.item_marker = Some(match checked {
+                    true => Span {
+                        text: "☑ ".to_string(),
+                        style: Style::color(Color::Green),
+                    },
+                    false => Span {
+                        text: "☐ ".to_string(),
+                        style: Style::color(Color::Cyan),
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn start_tag(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.styles.push(Style {
+                    color: Some(match level {
+                        HeadingLevel::H1 => Color::Magenta,
+                        HeadingLevel::H2 => Color::Cyan,
+                        _ => Color::Blue,
+                    }),
+                    weight: Weight::Bold,
+                    ..Default::default()
+                });
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.in_code_block = true;
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(List {
+                    next_number: start,
+                    marker_width: 0,
+                });
+            }
+            Tag::Item => {
+                self.flush();
+
+                let Some(list) = self.lists.last_mut() else {
+                    return;
+                };
+
+                let marker = match list.next_number.as_mut() {
+                    Some(number) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    None => "• ".to_string(),
+                };
+
+                list.marker_width = marker.width();
+                self.item_marker = Some(Span {
+                    text: marker,
+                    style: Style::color(Color::Cyan),
+                });
+            }
+            Tag::Emphasis => self.styles.push(Style::color(Color::Yellow)),
+            Tag::Strong => self.styles.push(Style {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            Tag::Strikethrough => self.styles.push(Style::color(Color::DarkGrey)),
+            Tag::Link { dest_url, .. } => {
+                self.styles.push(Style {
+                    color: Some(Color::Blue),
+                    underline: true,
+                    ..Default::default()
+                });
+                self.link_urls.push(dest_url.to_string());
+            }
+            Tag::Image { dest_url, .. } => {
+                self.push_text("image: ", Style::color(Color::DarkGrey));
+                self.styles.push(Style::color(Color::Blue));
+                self.link_urls.push(dest_url.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn end_tag(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush();
+
+                // Items of tight lists aren't wrapped in paragraphs, so this only
+                // separates the paragraphs of loose lists
+                self.push_blank_line();
+            }
+            TagEnd::Heading(_) => {
+                self.flush();
+                self.styles.pop();
+                self.push_blank_line();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth -= 1;
+                self.push_blank_line();
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.push_blank_line();
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+
+                if self.lists.is_empty() {
+                    self.push_blank_line();
+                }
+            }
+            TagEnd::Item => self.flush(),
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link | TagEnd::Image => {
+                self.styles.pop();
+
+                let Some(url) = self.link_urls.pop() else {
+                    return;
+                };
+
+                // Autolinks already show their target
+                let text = self.spans.last().map(|span| span.text.as_str());
+                if !url.is_empty() && text != Some(url.as_str()) {
+                    self.push_text(&format!(" ({})", url), Style::color(Color::DarkGrey));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_text(&mut self, text: &str, style: Style) {
+        let style = self
+            .styles
+            .iter()
+            .fold(Style::default(), |acc, style| acc.merge(*style))
+            .merge(style);
+
+        self.spans.push(Span {
+            text: text.to_string(),
+            style,
+        });
+    }
+
+    /// Code blocks keep their line breaks and are split at the width of
+    /// the pane instead of being word wrapped
+    fn push_code_block(&mut self, text: &str) {
+        let (first_prefix, prefix) = self.prefixes();
+        let available = self.available_width(&prefix);
+        let style = Style::color(Color::White);
+
+        for (index, source_line) in text.lines().enumerate() {
+            let source_line = source_line.replace('\t', "    ");
+            let mut chunks = split_at_width(&source_line, available);
+
+            if chunks.is_empty() {
+                chunks.push(String::new());
+            }
+
+            for (chunk_index, chunk) in chunks.into_iter().enumerate() {
+                let mut spans = match index == 0 && chunk_index == 0 {
+                    true => first_prefix.clone(),
+                    false => prefix.clone(),
+                };
+                spans.push(Span { text: chunk, style });
+
+                self.lines.push(Line {
+                    spans,
+                    background: Some(Color::DarkGrey),
+                });
+            }
+        }
+
+        self.item_marker = None;
+    }
+
+    /// Wraps the pending inline content into lines
+    fn flush(&mut self) {
+        if self.spans.is_empty() {
+            return;
+        }
+
+        let spans = mem::take(&mut self.spans);
+        let (first_prefix, prefix) = self.prefixes();
+        let available = self.available_width(&prefix);
+
+        for (index, wrapped) in wrap(&spans, available).into_iter().enumerate() {
+            let mut line = match index {
+                0 => first_prefix.clone(),
+                _ => prefix.clone(),
+            };
+            line.extend(wrapped);
+
+            self.lines.push(Line {
+                spans: line,
+                background: None,
+            });
+        }
+
+        self.item_marker = None;
+    }
+
+    fn push_blank_line(&mut self) {
+        if self.lines.last().is_some_and(|line| !line.is_blank()) {
+            self.lines.push(Line::default());
+        }
+    }
+
+    /// Returns the spans which start the first line of a block, and those
+    /// which start every line after it
+    fn prefixes(&self) -> (Vec<Span>, Vec<Span>) {
+        let mut prefix = vec![];
+
+        if self.quote_depth > 0 {
+            prefix.push(Span {
+                text: "│ ".repeat(self.quote_depth),
+                style: Style::color(Color::DarkGrey),
+            });
+        }
+
+        let indent = self
+            .lists
+            .iter()
+            .map(|list| list.marker_width)
+            .sum::<usize>();
+
+        let Some(marker) = self.item_marker.clone() else {
+            if indent > 0 {
+                prefix.push(Span {
+                    text: " ".repeat(indent),
+                    style: Style::default(),
+                });
+            }
+
+            return (prefix.clone(), prefix);
+        };
+
+        // The marker takes the place of the innermost level of indentation
+        let outer_indent = indent - self.lists.last().map_or(0, |list| list.marker_width);
+        let mut first_prefix = prefix.clone();
+
+        first_prefix.push(Span {
+            text: " ".repeat(outer_indent),
+            style: Style::default(),
+        });
+        first_prefix.push(marker.clone());
+
+        prefix.push(Span {
+            text: " ".repeat(outer_indent + marker.text.width()),
+            style: Style::default(),
+        });
+
+        (first_prefix, prefix)
+    }
+
+    fn available_width(&self, prefix: &[Span]) -> usize {
+        let prefix_width = prefix.iter().map(|span| span.text.width()).sum::<usize>();
+
+        // Always leave some room for content, even when deeply nested
+        self.width
+            .saturating_sub(prefix_width)
+            .max(8.min(self.width))
+    }
+}
+
+/// Greedily word wraps `spans` so that no line is wider than `width`.
+/// Words which are wider than a line on their own are split.
+fn wrap(spans: &[Span], width: usize) -> Vec<Vec<Span>> {
+    let mut lines: Vec<Vec<Span>> = vec![vec![]];
+    let mut line_width = 0;
+
+    for span in spans {
+        for piece in span.text.split_inclusive(char::is_whitespace) {
+            let word = piece.trim_end();
+            let space = &piece[word.len()..];
+            let word_width = word.width();
+
+            if line_width > 0 && line_width + word_width > width {
+                lines.push(vec![]);
+                line_width = 0;
+            }
+
+            let mut chunks = split_at_width(word, width);
+            let last_chunk = chunks.pop();
+
+            for chunk in chunks {
+                push_to_line(lines.last_mut().unwrap(), chunk, span.style);
+                lines.push(vec![]);
+                line_width = 0;
+            }
+
+            if let Some(chunk) = last_chunk {
+                line_width += chunk.width();
+                push_to_line(lines.last_mut().unwrap(), chunk, span.style);
+            }
+
+            // Whitespace is dropped at the start of a wrapped line
+            if !space.is_empty() && line_width > 0 && line_width < width {
+                line_width += 1;
+                push_to_line(lines.last_mut().unwrap(), " ".to_string(), span.style);
+            }
+        }
+    }
+
+    lines
+}
+
+fn push_to_line(line: &mut Vec<Span>, text: String, style: Style) {
+    match line.last_mut() {
+        Some(span) if span.style == style => span.text.push_str(&text),
+        _ => line.push(Span { text, style }),
+    }
+}
+
+/// Splits `text` into chunks which are no wider than `width`
+fn split_at_width(text: &str, width: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut chunk = String::new();
+    let mut chunk_width = 0;
+
+    for char in text.chars() {
+        let char_width = char.width().unwrap_or(0);
+
+        if chunk_width + char_width > width && !chunk.is_empty() {
+            chunks.push(mem::take(&mut chunk));
+            chunk_width = 0;
+        }
+
+        chunk.push(char);
+        chunk_width += char_width;
+    }
+
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of every line, as it would be shown
+    fn text(content: &str, width: usize) -> Vec<String> {
+        render(content, width)
+            .iter()
+            .map(|line| line.spans.iter().map(|span| span.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn paragraphs_wrap_between_words() {
+        let content = "the quick brown fox jumps over the lazy dog";
+        let lines = text(content, 15);
+
+        assert!(lines.iter().all(|line| line.width() <= 15));
+        assert_eq!(
+            lines
+                .iter()
+                .flat_map(|line| line.split_whitespace())
+                .collect::<Vec<&str>>(),
+            content.split_whitespace().collect::<Vec<&str>>()
+        );
+        assert_eq!(lines.len(), 3);
+
+        // Words wider than a line are split
+        assert_eq!(
+            text("a supercalifragilistic word", 8),
+            ["a ", "supercal", "ifragili", "stic ", "word"]
+        );
+    }
+
+    #[test]
+    fn nested_lists_are_indented_under_their_marker() {
+        assert_eq!(
+            text(
+                "- one\n- two\n  - nested item that wraps around\n    1. deep\n",
+                20
+            ),
+            [
+                "• one",
+                "• two",
+                "  • nested item that",
+                "    wraps around",
+                "    1. deep"
+            ]
+        );
+        assert_eq!(text("3. third\n4. fourth\n", 20), ["3. third", "4. fourth"]);
+    }
+
+    #[test]
+    fn code_blocks_keep_their_lines_and_are_split_at_the_width() {
+        let lines = render("```\nfn main() {\n\tlet long_line = 1;\n}\n```\n", 12);
+
+        assert!(lines.iter().all(|line| line.background.is_some()));
+        assert_eq!(
+            text("```\nfn main() {\n\tlet long_line = 1;\n}\n```\n", 12),
+            ["fn main() {", "    let long", "_line = 1;", "}"]
+        );
+    }
+
+    #[test]
+    fn wide_characters_count_as_two_columns() {
+        let lines = text("日本語のテキストです", 7);
+
+        assert_eq!(lines, ["日本語", "のテキ", "ストで", "す"]);
+        assert!(lines.iter().all(|line| line.width() <= 7));
+    }
+}
@@ -0,0 +1,536 @@
+use crate::database::Entry;
+use crate::search::{self, ContentScores, Term};
+use crate::tags;
+use anyhow::{Result, anyhow};
+use chrono::{Days, Local, NaiveDate, TimeDelta, TimeZone};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+/// A parsed search query.
+///
+/// Examples of the syntax:
+/// - `standup notes`: free text, fuzzy matched against the title, tags and content
+/// - `"weekly review"`: a phrase, which has to appear exactly
+/// - `tag:work`, `-tag:draft`, `is:favorite`, `title:"standup"`
+/// - `modified:>2026-01-01`, `created:<=2026-02-01`, `created:last-7d`
+/// - `tag:work OR tag:home`, `(tag:work OR tag:home) -is:favorite`
+///
+/// Words next to each other all have to match, `OR` binds looser than that.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    /// Free text or a quoted phrase
+    Text(Term),
+    Title(Term),
+    Tag(String),
+    IsFavorite,
+    Created(TimeRange),
+    Modified(TimeRange),
+}
+
+/// Half open range of unix timestamps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl TimeRange {
+    fn contains(&self, timestamp: u64) -> bool {
+        self.start.is_none_or(|start| timestamp >= start)
+            && self.end.is_none_or(|end| timestamp < end)
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query> {
+        let tokens = tokenize(query)?;
+
+        // Smart case, like fzf and vim, applied to the whole query. Only text
+        // that gets matched counts, not keywords like `OR` or field names
+        let case_sensitive = tokens.iter().any(|token| match token {
+            Token::Word(text) | Token::Phrase(text) => text.chars().any(char::is_uppercase),
+            Token::Field(name, value) => name == "title" && value.chars().any(char::is_uppercase),
+            _ => false,
+        });
+
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+            case_sensitive,
+        };
+
+        let query = parser.parse_or()?;
+
+        match parser.tokens.next() {
+            Some(Token::RightParen) => Err(anyhow!("unmatched `)`")),
+            Some(token) => Err(anyhow!("unexpected {}", token)),
+            None => Ok(query),
+        }
+    }
+
+    /// Returns how well `entry` matches, or `None` if it doesn't match at all.
+    ///
+    /// Only text contributes to the score, everything else is a plain filter.
+    /// Ranges of the title which matched get pushed onto `title_matches`.
+    pub fn score(
+        &self,
+        entry: &Entry,
+        content_scores: &ContentScores,
+        title_matches: &mut Vec<Range<usize>>,
+    ) -> Option<i64> {
+        match self {
+            Query::And(queries) => queries
+                .iter()
+                .map(|query| query.score(entry, content_scores, title_matches))
+                .sum(),
+            Query::Or(queries) => queries
+                .iter()
+                .filter_map(|query| query.score(entry, content_scores, title_matches))
+                .max(),
+            Query::Not(query) => match query.score(entry, content_scores, &mut vec![]) {
+                Some(_) => None,
+                None => Some(0),
+            },
+            Query::Text(term) => search::score_text(entry, term, content_scores, title_matches),
+            Query::Title(term) => search::score_title(entry, term, title_matches),
+            Query::Tag(tag) => entry
+                .tags
+                .iter()
+                .any(|entry_tag| tags::is_same_or_parent(tag, entry_tag))
+                .then_some(0),
+            Query::IsFavorite => entry.favorite.then_some(0),
+            Query::Created(range) => range.contains(entry.created_at).then_some(0),
+            Query::Modified(range) => range.contains(entry.modified_at).then_some(0),
+        }
+    }
+
+    /// Every free text term in the query, which are the only ones matched against content
+    pub fn text_terms(&self) -> Vec<&Term> {
+        match self {
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(Query::text_terms).collect()
+            }
+            Query::Not(query) => query.text_terms(),
+            Query::Text(term) => vec![term],
+            _ => vec![],
+        }
+    }
+}
+
+/// Names which make a word like `tag:work` a field, any other word with a `:`
+/// in it is just text, eg: `10:30`
+const FIELDS: [&str; 5] = ["tag", "title", "is", "created", "modified"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Or,
+    Minus,
+    Word(String),
+    Phrase(String),
+    /// eg: `tag:work`, the value is kept as-is when it was quoted
+    Field(String, String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LeftParen => write!(f, "`(`"),
+            Token::RightParen => write!(f, "`)`"),
+            Token::Or => write!(f, "`OR`"),
+            Token::Minus => write!(f, "`-`"),
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Phrase(phrase) => write!(f, "`\"{}\"`", phrase),
+            Token::Field(name, value) => write!(f, "`{}:{}`", name, value),
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = query.chars().peekable();
+
+    while let Some(&char) = chars.peek() {
+        match char {
+            _ if char.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            '"' => tokens.push(Token::Phrase(read_quoted(&mut chars)?)),
+            // A lone `-` is just text, it only negates what directly follows it
+            '-' => {
+                chars.next();
+
+                match chars.peek() {
+                    Some(next) if !next.is_whitespace() => tokens.push(Token::Minus),
+                    _ => tokens.push(Token::Word("-".to_string())),
+                }
+            }
+            _ => {
+                let mut word = String::new();
+
+                while let Some(&char) = chars.peek() {
+                    if char.is_whitespace() || "()\"".contains(char) {
+                        break;
+                    }
+
+                    word.push(char);
+                    chars.next();
+                }
+
+                let token = match word.split_once(':') {
+                    Some((name, "")) if FIELDS.contains(&name) && chars.peek() == Some(&'"') => {
+                        Token::Field(name.to_string(), read_quoted(&mut chars)?)
+                    }
+                    Some((name, value)) if FIELDS.contains(&name) => {
+                        Token::Field(name.to_string(), value.to_string())
+                    }
+                    _ if word == "OR" => Token::Or,
+                    _ => Token::Word(word),
+                };
+
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut Peekable<Chars>) -> Result<String> {
+    // Skip the opening quote
+    chars.next();
+
+    let mut text = String::new();
+
+    for char in chars.by_ref() {
+        if char == '"' {
+            return Ok(text);
+        }
+
+        text.push(char);
+    }
+
+    Err(anyhow!("missing closing `\"`"))
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+    case_sensitive: bool,
+}
+
+impl Parser {
+    fn parse_or(&mut self) -> Result<Query> {
+        let mut queries = vec![self.parse_and()?];
+
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            let query = self.parse_and()?;
+
+            if query == Query::And(vec![]) {
+                return Err(anyhow!("expected something after `OR`"));
+            }
+
+            queries.push(query);
+        }
+
+        match queries.len() {
+            1 => Ok(queries.remove(0)),
+            _ => Ok(Query::Or(queries)),
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Query> {
+        let mut queries = vec![];
+
+        while let Some(token) = self.tokens.peek() {
+            if *token == Token::Or || *token == Token::RightParen {
+                break;
+            }
+
+            queries.push(self.parse_unary()?);
+        }
+
+        // `OR` with nothing on one side of it is almost certainly a mistake
+        if queries.is_empty() && self.tokens.peek() == Some(&Token::Or) {
+            return Err(anyhow!("expected something before `OR`"));
+        }
+
+        match queries.len() {
+            1 => Ok(queries.remove(0)),
+            _ => Ok(Query::And(queries)),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Query> {
+        match self.tokens.next() {
+            Some(Token::Minus) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LeftParen) => {
+                let query = self.parse_or()?;
+
+                match self.tokens.next() {
+                    Some(Token::RightParen) => Ok(query),
+                    _ => Err(anyhow!("missing closing `)`")),
+                }
+            }
+            Some(Token::Word(word)) => Ok(Query::Text(self.term(&word, false))),
+            Some(Token::Phrase(phrase)) => Ok(Query::Text(self.term(&phrase, true))),
+            Some(Token::Field(name, value)) => self.parse_field(&name, &value),
+            Some(token) => Err(anyhow!("unexpected {}", token)),
+            None => Err(anyhow!("unexpected end of query")),
+        }
+    }
+
+    fn parse_field(&self, name: &str, value: &str) -> Result<Query> {
+        if value.is_empty() {
+            return Err(anyhow!("`{}:` needs a value", name));
+        }
+
+        match name {
+            "tag" => Ok(Query::Tag(value.to_string())),
+            "title" => Ok(Query::Title(self.term(value, true))),
+            "is" => match value {
+                "favorite" | "fav" => Ok(Query::IsFavorite),
+                _ => Err(anyhow!(
+                    "unknown value `is:{}`, expected `is:favorite`",
+                    value
+                )),
+            },
+            "created" => Ok(Query::Created(parse_time_range(value)?)),
+            "modified" => Ok(Query::Modified(parse_time_range(value)?)),
+            _ => Err(anyhow!("unknown field `{}:`", name)),
+        }
+    }
+
+    fn term(&self, text: &str, exact: bool) -> Term {
+        Term::new(text, exact, self.case_sensitive)
+    }
+}
+
+/// Parses either a relative range, like `last-7d`, or a date with an optional
+/// comparison in front of it, like `>=2026-01-01`.
+///
+/// Dates are whole days in the local timezone, so `>2026-01-01` starts at
+/// midnight on the 2nd, and a bare date matches anything on that day.
+/// Relative ranges support hours, days, weeks, months (30 days) and years (365 days).
+fn parse_time_range(value: &str) -> Result<TimeRange> {
+    if let Some(amount) = value.strip_prefix("last-") {
+        let now = Local::now();
+        let Some(unit) = amount.chars().last() else {
+            return Err(anyhow!(
+                "expected something like `last-7d`, found `{}`",
+                value
+            ));
+        };
+        let count = amount[..amount.len() - unit.len_utf8()]
+            .parse::<i64>()
+            .map_err(|_| anyhow!("expected something like `last-7d`, found `{}`", value))?;
+
+        let duration = match unit {
+            'h' => TimeDelta::try_hours(count),
+            'd' => TimeDelta::try_days(count),
+            'w' => TimeDelta::try_weeks(count),
+            'm' => count.checked_mul(30).and_then(TimeDelta::try_days),
+            'y' => count.checked_mul(365).and_then(TimeDelta::try_days),
+            _ => {
+                return Err(anyhow!(
+                    "unknown unit `{}` in `{}`, use h, d, w, m or y",
+                    unit,
+                    value
+                ));
+            }
+        };
+
+        let start = duration
+            .and_then(|duration| now.checked_sub_signed(duration))
+            .ok_or(anyhow!("`{}` is too far back", value))?;
+
+        return Ok(TimeRange {
+            start: Some(start.timestamp().max(0) as u64),
+            end: None,
+        });
+    }
+
+    let (comparison, date) = match value.find(|char: char| char.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => {
+            return Err(anyhow!(
+                "expected a date like `2026-01-01`, found `{}`",
+                value
+            ));
+        }
+    };
+
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow!("expected a date like `2026-01-01`, found `{}`", date))?;
+
+    let day_start = start_of_day(date)?;
+    let day_end = start_of_day(
+        date.checked_add_days(Days::new(1))
+            .ok_or(anyhow!("`{}` is too far ahead", value))?,
+    )?;
+
+    let (start, end) = match comparison {
+        "" | "=" => (Some(day_start), Some(day_end)),
+        ">" => (Some(day_end), None),
+        ">=" => (Some(day_start), None),
+        "<" => (None, Some(day_start)),
+        "<=" => (None, Some(day_end)),
+        _ => return Err(anyhow!("unknown comparison `{}`", comparison)),
+    };
+
+    Ok(TimeRange { start, end })
+}
+
+fn start_of_day(date: NaiveDate) -> Result<u64> {
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or(anyhow!("invalid date"))?;
+
+    let timestamp = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .ok_or(anyhow!("{} doesn't exist in the local timezone", date))?
+        .timestamp();
+
+    Ok(timestamp.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Query {
+        Query::Text(Term::new(text, false, false))
+    }
+
+    #[test]
+    fn implicit_and_binds_tighter_than_or() {
+        assert_eq!(
+            Query::parse("a b OR c").unwrap(),
+            Query::Or(vec![Query::And(vec![text("a"), text("b")]), text("c")])
+        );
+
+        assert_eq!(
+            Query::parse("a (b OR c)").unwrap(),
+            Query::And(vec![text("a"), Query::Or(vec![text("b"), text("c")])])
+        );
+    }
+
+    #[test]
+    fn parses_fields_and_negation() {
+        assert_eq!(
+            Query::parse("-tag:draft is:fav").unwrap(),
+            Query::And(vec![
+                Query::Not(Box::new(Query::Tag("draft".to_string()))),
+                Query::IsFavorite,
+            ])
+        );
+
+        assert_eq!(
+            Query::parse("title:\"weekly review\"").unwrap(),
+            Query::Title(Term::new("weekly review", true, false))
+        );
+    }
+
+    #[test]
+    fn words_with_colons_are_text_unless_they_name_a_field() {
+        assert_eq!(Query::parse("10:30").unwrap(), text("10:30"));
+        assert_eq!(Query::parse("http://x").unwrap(), text("http://x"));
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        for query in [
+            "(a",
+            "a)",
+            "\"a",
+            "OR a",
+            "a OR",
+            "tag:",
+            "is:draft",
+            "created:yesterday",
+            "created:~2026-01-01",
+        ] {
+            assert!(Query::parse(query).is_err(), "{} should not parse", query);
+        }
+    }
+
+    #[test]
+    fn smart_case_ignores_keywords_and_field_names() {
+        assert_eq!(
+            Query::parse("standup OR zzz").unwrap(),
+            Query::Or(vec![text("standup"), text("zzz")])
+        );
+        assert_eq!(
+            Query::parse("tag:x OR standup").unwrap(),
+            Query::Or(vec![Query::Tag("x".to_string()), text("standup")])
+        );
+
+        assert_eq!(
+            Query::parse("Standup").unwrap(),
+            Query::Text(Term::new("Standup", false, true))
+        );
+        assert_eq!(
+            Query::parse("title:Standup zzz").unwrap(),
+            Query::And(vec![
+                Query::Title(Term::new("Standup", true, true)),
+                Query::Text(Term::new("zzz", false, true)),
+            ])
+        );
+    }
+
+    #[test]
+    fn relative_ranges_reject_bad_amounts_without_panicking() {
+        for value in [
+            "last-",
+            "last-d",
+            "last-7",
+            "last-7x",
+            "last-é",
+            "last-99999999999999d",
+            "last-99999999999999h",
+            "last-9999999999999999w",
+            "last-999999999999999999m",
+            "last-999999999999999999y",
+        ] {
+            assert!(
+                parse_time_range(value).is_err(),
+                "{} should not parse",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn relative_ranges_start_in_the_past() {
+        let now = Local::now().timestamp() as u64;
+        let range = parse_time_range("last-7d").unwrap();
+
+        assert_eq!(range.end, None);
+        assert!(range.start.unwrap().abs_diff(now - 7 * 24 * 60 * 60) <= 1);
+    }
+
+    #[test]
+    fn dates_cover_whole_days() {
+        let day_start = start_of_day(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()).unwrap();
+        let day_end = start_of_day(NaiveDate::from_ymd_opt(2026, 1, 2).unwrap()).unwrap();
+
+        let range = parse_time_range("2026-01-01").unwrap();
+        assert_eq!((range.start, range.end), (Some(day_start), Some(day_end)));
+
+        let range = parse_time_range(">2026-01-01").unwrap();
+        assert_eq!((range.start, range.end), (Some(day_end), None));
+
+        let range = parse_time_range("<=2026-01-01").unwrap();
+        assert_eq!((range.start, range.end), (None, Some(day_end)));
+    }
+}
@@ -0,0 +1,671 @@
+use crate::database::Entry;
+use crate::index::{self, Index};
+use crate::query::Query;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+// Loosely based on the scoring used by fzf
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 8;
+const BONUS_BOUNDARY: i64 = 10;
+const PENALTY_GAP_START: i64 = -3;
+const PENALTY_GAP_EXTENSION: i64 = -1;
+
+// A hit in the title says more about an entry than a hit in a tag, which in
+// turn says more than a hit somewhere in the content
+const TITLE_WEIGHT: i64 = 3;
+const TAG_WEIGHT: i64 = 2;
+const CONTENT_WEIGHT: i64 = 1;
+
+#[derive(Debug, Default, Clone)]
+pub struct SearchResult {
+    /// Index of the matching entry in `Database::entries`
+    pub index: usize,
+    pub score: i64,
+    /// Byte ranges of the title which matched the query, used for highlighting
+    pub title_matches: Vec<Range<usize>>,
+}
+
+/// Orders in which search results can be listed
+#[derive(Deserialize, Serialize, ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Best matches first, and most recently modified first amongst equal matches
+    #[default]
+    Relevance,
+    ModifiedDesc,
+    ModifiedAsc,
+    CreatedDesc,
+    CreatedAsc,
+    /// Alphabetically, with numbers in titles compared by their value
+    Title,
+    /// Longest content first
+    Size,
+}
+
+impl SortOrder {
+    const ALL: [SortOrder; 7] = [
+        SortOrder::Relevance,
+        SortOrder::ModifiedDesc,
+        SortOrder::ModifiedAsc,
+        SortOrder::CreatedDesc,
+        SortOrder::CreatedAsc,
+        SortOrder::Title,
+        SortOrder::Size,
+    ];
+
+    /// The order after this one, wrapping around after the last
+    pub fn next(self) -> SortOrder {
+        let position = SortOrder::ALL
+            .iter()
+            .position(|order| *order == self)
+            .unwrap_or_default();
+
+        SortOrder::ALL[(position + 1) % SortOrder::ALL.len()]
+    }
+
+    /// Short description, eg: `modified ↓` for the most recently modified first
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "relevance",
+            SortOrder::ModifiedDesc => "modified ↓",
+            SortOrder::ModifiedAsc => "modified ↑",
+            SortOrder::CreatedDesc => "created ↓",
+            SortOrder::CreatedAsc => "created ↑",
+            SortOrder::Title => "title",
+            SortOrder::Size => "size",
+        }
+    }
+}
+
+/// Sorts `results` of a search through `entries` by `order`.
+/// Stable, so entries which compare equal keep their relative order.
+pub fn sort(results: &mut [SearchResult], entries: &[Entry], order: SortOrder) {
+    let entry = |result: &SearchResult| &entries[result.index];
+
+    match order {
+        SortOrder::Relevance => results.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(entry(b).modified_at.cmp(&entry(a).modified_at))
+        }),
+        SortOrder::ModifiedDesc => results.sort_by_key(|result| Reverse(entry(result).modified_at)),
+        SortOrder::ModifiedAsc => results.sort_by_key(|result| entry(result).modified_at),
+        SortOrder::CreatedDesc => results.sort_by_key(|result| Reverse(entry(result).created_at)),
+        SortOrder::CreatedAsc => results.sort_by_key(|result| entry(result).created_at),
+        SortOrder::Title => results.sort_by(|a, b| natural_cmp(&entry(a).title, &entry(b).title)),
+        SortOrder::Size => results.sort_by_key(|result| Reverse(entry(result).content.len())),
+    }
+}
+
+/// Compares text the way people tend to expect, ignoring case and comparing
+/// runs of digits by their value, eg: `day 2` comes before `Day 10`
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let (a_char, b_char) = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) => (*a_char, *b_char),
+        };
+
+        if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+            let a_number = take_digits(&mut a_chars);
+            let b_number = take_digits(&mut b_chars);
+
+            // Without leading zeroes, a longer number is always a bigger one
+            let a_trimmed = a_number.trim_start_matches('0');
+            let b_trimmed = b_number.trim_start_matches('0');
+
+            let ordering = a_trimmed
+                .len()
+                .cmp(&b_trimmed.len())
+                .then_with(|| a_trimmed.cmp(b_trimmed));
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+
+            continue;
+        }
+
+        let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+
+        a_chars.next();
+        b_chars.next();
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+
+    while let Some(char) = chars.next_if(char::is_ascii_digit) {
+        digits.push(char);
+    }
+
+    digits
+}
+
+/// A single fuzzy match of a term against some text
+#[derive(Debug)]
+struct Match {
+    score: i64,
+    /// Byte offsets of the matched characters
+    positions: Vec<usize>,
+    /// Amount of characters between the first and last matched character, inclusive
+    span: usize,
+}
+
+/// A piece of text to look for in an entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    chars: Vec<char>,
+    /// Exact terms have to appear as-is, rather than as a subsequence
+    exact: bool,
+    case_sensitive: bool,
+}
+
+impl Term {
+    pub fn new(text: &str, exact: bool, case_sensitive: bool) -> Term {
+        Term {
+            chars: text
+                .chars()
+                .map(|char| fold_case(char, case_sensitive))
+                .collect(),
+            exact,
+            case_sensitive,
+        }
+    }
+
+    fn find(&self, haystack: &str) -> Option<Match> {
+        match self.exact {
+            true => exact_match(haystack, &self.chars, self.case_sensitive),
+            false => fuzzy_match(haystack, &self.chars, self.case_sensitive),
+        }
+    }
+
+    /// Looks the term up in the index, and returns the best content score of
+    /// every document it appears in.
+    ///
+    /// The index is case-folded and split into words, so content is always matched
+    /// case insensitively, and a fuzzy term has to fit within a single word.
+    /// The last word of a phrase only has to be the start of a word, as it's
+    /// likely still being typed.
+    fn content_scores(&self, index: &Index) -> HashMap<u32, i64> {
+        let mut scores = HashMap::new();
+        let mut add = |document: u32, score: i64| {
+            let best = scores.entry(document).or_insert(score);
+            *best = (*best).max(score);
+        };
+
+        match self.exact {
+            false => {
+                let chars = self
+                    .chars
+                    .iter()
+                    .filter(|char| char.is_alphanumeric())
+                    .flat_map(|char| char.to_lowercase())
+                    .collect::<Vec<char>>();
+
+                for (token, postings) in index.tokens() {
+                    if !is_subsequence(token, &chars) {
+                        continue;
+                    }
+
+                    // Only tight matches count, otherwise a short term would be
+                    // found scattered across almost any word
+                    let Some(token_match) = fuzzy_match(token, &chars, false)
+                        .filter(|token_match| token_match.span <= chars.len() * 2)
+                    else {
+                        continue;
+                    };
+
+                    for posting in postings {
+                        add(posting.document, token_match.score);
+                    }
+                }
+            }
+            true => {
+                let text = self.chars.iter().collect::<String>();
+                let tokens = index::tokenize(&text).collect::<Vec<(u32, String)>>();
+                let Some(score) = exact_match(&text, &self.chars, true).map(|text| text.score)
+                else {
+                    return scores;
+                };
+
+                let Some(((_, last), rest)) = tokens.split_last() else {
+                    return scores;
+                };
+
+                let mut positions = rest
+                    .iter()
+                    .map(|(_, token)| {
+                        index
+                            .postings(token)
+                            .iter()
+                            .map(|posting| (posting.document, posting.positions.clone()))
+                            .collect::<HashMap<u32, Vec<u32>>>()
+                    })
+                    .collect::<Vec<HashMap<u32, Vec<u32>>>>();
+
+                let mut last_positions: HashMap<u32, Vec<u32>> = HashMap::new();
+
+                for (_, postings) in index.tokens().filter(|(token, _)| token.starts_with(last)) {
+                    for posting in postings {
+                        last_positions
+                            .entry(posting.document)
+                            .or_default()
+                            .extend(&posting.positions);
+                    }
+                }
+
+                for document_positions in last_positions.values_mut() {
+                    document_positions.sort_unstable();
+                }
+
+                positions.push(last_positions);
+
+                // How far every word is from the first one, which skips over words
+                // too long to be indexed
+                let offsets = tokens
+                    .iter()
+                    .map(|(position, _)| position - tokens[0].0)
+                    .collect::<Vec<u32>>();
+
+                // Every occurrence of the first word is a possible start of the phrase
+                for (&document, starts) in &positions[0] {
+                    let is_match = starts.iter().any(|&start| {
+                        positions[1..]
+                            .iter()
+                            .zip(&offsets[1..])
+                            .all(|(token_positions, offset)| {
+                                token_positions
+                                    .get(&document)
+                                    .is_some_and(|token_positions| {
+                                        token_positions.binary_search(&(start + offset)).is_ok()
+                                    })
+                            })
+                    });
+
+                    if is_match {
+                        add(document, score);
+                    }
+                }
+            }
+        }
+
+        scores
+    }
+}
+
+/// Content scores of every text term in a query, looked up in the index before
+/// going through the entries, so the index is only consulted once per term
+pub struct ContentScores<'a> {
+    index: &'a Index,
+    terms: Vec<(&'a Term, HashMap<u32, i64>)>,
+}
+
+impl<'a> ContentScores<'a> {
+    pub fn new(index: &'a Index, query: &'a Query) -> ContentScores<'a> {
+        ContentScores {
+            index,
+            terms: query
+                .text_terms()
+                .into_iter()
+                .map(|term| (term, term.content_scores(index)))
+                .collect(),
+        }
+    }
+
+    fn get(&self, entry: &Entry, term: &Term) -> Option<i64> {
+        let document = self.index.document_id(&entry.path)?;
+
+        self.terms
+            .iter()
+            .find(|(query_term, _)| std::ptr::eq(*query_term, term))?
+            .1
+            .get(&document)
+            .copied()
+    }
+}
+
+/// Filters `entries` down to those matching `query`, and sorts them so that
+/// the best matches come first.
+///
+/// Favorites get a slight boost over otherwise equal matches.
+/// An empty query matches every entry in its original order.
+pub fn rank(entries: &[Entry], index: &Index, query: &Query) -> Vec<SearchResult> {
+    let content_scores = ContentScores::new(index, query);
+
+    let mut results = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let mut title_matches = vec![];
+            let mut score = query.score(entry, &content_scores, &mut title_matches)?;
+
+            if entry.favorite {
+                score += score / 10;
+            }
+
+            Some(SearchResult {
+                index,
+                score,
+                title_matches,
+            })
+        })
+        .collect::<Vec<SearchResult>>();
+
+    // Stable, so that equally good matches keep their original order
+    results.sort_by_key(|result| -result.score);
+
+    results
+}
+
+/// Returns the best weighted score of `term` against the title, tags and content
+/// of `entry`, or `None` if it doesn't match any of them
+pub fn score_text(
+    entry: &Entry,
+    term: &Term,
+    content_scores: &ContentScores,
+    title_matches: &mut Vec<Range<usize>>,
+) -> Option<i64> {
+    let title_score = score_title(entry, term, title_matches);
+
+    let tag_score = entry
+        .tags
+        .iter()
+        .filter_map(|tag| term.find(tag))
+        .map(|tag_match| tag_match.score)
+        .max();
+
+    let content_score = content_scores.get(entry, term);
+
+    [
+        title_score,
+        tag_score.map(|score| score * TAG_WEIGHT),
+        content_score.map(|score| score * CONTENT_WEIGHT),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+}
+
+/// Like `score_text`, but only looks at the title
+pub fn score_title(
+    entry: &Entry,
+    term: &Term,
+    title_matches: &mut Vec<Range<usize>>,
+) -> Option<i64> {
+    let title_match = term.find(&entry.title)?;
+
+    title_matches.extend(title_match.positions.iter().map(|&position| {
+        let char_length = entry.title[position..]
+            .chars()
+            .next()
+            .map_or(1, char::len_utf8);
+        position..position + char_length
+    }));
+
+    Some(title_match.score * TITLE_WEIGHT)
+}
+
+/// Finds the first occurrence of `term` in `haystack`, scored the same way as a fuzzy match
+fn exact_match(haystack: &str, term: &[char], case_sensitive: bool) -> Option<Match> {
+    if term.is_empty() {
+        return None;
+    }
+
+    let chars = haystack.char_indices().collect::<Vec<(usize, char)>>();
+
+    let start = (0..(chars.len() + 1).saturating_sub(term.len())).find(|&start| {
+        chars[start..start + term.len()]
+            .iter()
+            .zip(term)
+            .all(|((_, char), term_char)| fold_case(*char, case_sensitive) == *term_char)
+    })?;
+
+    let mut score = 0;
+
+    for index in start..start + term.len() {
+        score += SCORE_MATCH + BONUS_CONSECUTIVE;
+
+        if is_boundary(&chars, index) {
+            score += BONUS_BOUNDARY;
+        }
+    }
+
+    Some(Match {
+        // The first character has nothing to be consecutive with
+        score: score - BONUS_CONSECUTIVE,
+        positions: chars[start..start + term.len()]
+            .iter()
+            .map(|(position, _)| *position)
+            .collect(),
+        span: term.len(),
+    })
+}
+
+/// Finds `term` as a subsequence of `haystack`.
+///
+/// The first occurrence is found scanning forwards, then we walk backwards from
+/// where it ended to find the shortest window that still contains the term, which
+/// is what gets scored.
+fn fuzzy_match(haystack: &str, term: &[char], case_sensitive: bool) -> Option<Match> {
+    if term.is_empty() {
+        return None;
+    }
+
+    let chars = haystack.char_indices().collect::<Vec<(usize, char)>>();
+    let matches = |index: usize, term_index: usize| {
+        fold_case(chars[index].1, case_sensitive) == term[term_index]
+    };
+
+    let mut term_index = 0;
+    let mut end = None;
+
+    for index in 0..chars.len() {
+        if matches(index, term_index) {
+            term_index += 1;
+
+            if term_index == term.len() {
+                end = Some(index);
+                break;
+            }
+        }
+    }
+
+    let end = end?;
+    let mut start = end;
+
+    for index in (0..=end).rev() {
+        if matches(index, term_index - 1) {
+            term_index -= 1;
+
+            if term_index == 0 {
+                start = index;
+                break;
+            }
+        }
+    }
+
+    let mut score = 0;
+    let mut positions = vec![];
+    let mut previous_matched = false;
+    let mut in_gap = false;
+
+    for index in start..=end {
+        if term_index < term.len() && matches(index, term_index) {
+            score += SCORE_MATCH;
+
+            if previous_matched {
+                score += BONUS_CONSECUTIVE;
+            }
+
+            if is_boundary(&chars, index) {
+                score += BONUS_BOUNDARY;
+            }
+
+            positions.push(chars[index].0);
+            term_index += 1;
+            previous_matched = true;
+            in_gap = false;
+        } else {
+            score += match in_gap {
+                true => PENALTY_GAP_EXTENSION,
+                false => PENALTY_GAP_START,
+            };
+
+            previous_matched = false;
+            in_gap = true;
+        }
+    }
+
+    Some(Match {
+        score,
+        positions,
+        span: end - start + 1,
+    })
+}
+
+/// Whether `term` appears in `haystack` in order, without allocating like `fuzzy_match` does
+fn is_subsequence(haystack: &str, term: &[char]) -> bool {
+    let mut remaining = term;
+
+    for char in haystack.chars() {
+        match remaining.split_first() {
+            Some((first, rest)) if *first == char => remaining = rest,
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    remaining.is_empty()
+}
+
+/// Whether the character at `index` starts a word, eg: the `b` in `foo_bar`, `foo bar` and `fooBar`
+fn is_boundary(chars: &[(usize, char)], index: usize) -> bool {
+    let Some(previous) = index.checked_sub(1).map(|index| chars[index].1) else {
+        return true;
+    };
+
+    let current = chars[index].1;
+
+    !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase())
+}
+
+fn fold_case(char: char, case_sensitive: bool) -> char {
+    match case_sensitive {
+        true => char,
+        false => char.to_lowercase().next().unwrap_or(char),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn entries(notes: &[(&str, &str)]) -> Vec<Entry> {
+        notes
+            .iter()
+            .map(|(title, content)| {
+                Entry::from_text(PathBuf::from(format!("/notes/{}.md", title)), content).unwrap()
+            })
+            .collect()
+    }
+
+    fn titles(notes: &[(&str, &str)], query: &str) -> Vec<String> {
+        let entries = entries(notes);
+        let mut index = Index::open(Path::new("/nonexistent/index"), Path::new("/notes"));
+        index.update(&entries);
+
+        rank(&entries, &index, &Query::parse(query).unwrap())
+            .into_iter()
+            .map(|result| entries[result.index].title.clone())
+            .collect()
+    }
+
+    #[test]
+    fn natural_cmp_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("day 2", "Day 10"), Ordering::Less);
+        assert_eq!(natural_cmp("day 10", "day 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("v007", "v7"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "ABD"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+
+        let mut names = vec!["note 10", "Note 1", "note 2", "apple"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["apple", "Note 1", "note 2", "note 10"]);
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_tight_matches_on_boundaries() {
+        let term = Term::new("fb", false, false);
+
+        let boundary = term.find("foo_bar").unwrap();
+        let scattered = term.find("xfxxxxbx").unwrap();
+
+        assert!(boundary.score > scattered.score);
+        assert_eq!(boundary.positions, [0, 4]);
+        assert!(term.find("bf").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_respects_case_sensitivity() {
+        assert!(Term::new("std", false, false).find("StandUp").is_some());
+        assert!(Term::new("Std", false, true).find("standup").is_none());
+        assert!(Term::new("SU", false, true).find("StandUp").is_some());
+    }
+
+    #[test]
+    fn phrases_match_words_in_order() {
+        let notes = [
+            ("in-order", "the weekly review went well"),
+            ("reversed", "a review of the weekly plan"),
+            ("apart", "weekly things and a review"),
+        ];
+
+        assert_eq!(titles(&notes, "\"weekly review\""), ["in-order"]);
+        assert_eq!(titles(&notes, "\"review weekly\""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn last_word_of_a_phrase_can_be_a_prefix() {
+        let notes = [("standup", "notes from the weekly review")];
+
+        assert_eq!(titles(&notes, "\"weekly rev\""), ["standup"]);
+        assert_eq!(titles(&notes, "\"week review\""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn phrases_do_not_match_across_words_too_long_to_index() {
+        let long = "x".repeat(100);
+        let content = format!("before {} after", long);
+        let notes = [("link", content.as_str())];
+
+        assert_eq!(titles(&notes, "\"before after\""), Vec::<String>::new());
+        assert_eq!(titles(&notes, &format!("\"{}\"", content)), ["link"]);
+    }
+
+    #[test]
+    fn or_matches_either_side() {
+        let notes = [("Standup", ""), ("retro", ""), ("planning", "")];
+
+        assert_eq!(titles(&notes, "standup OR retro"), ["Standup", "retro"]);
+        assert_eq!(titles(&notes, "title:standup OR zzz"), ["Standup"]);
+    }
+}
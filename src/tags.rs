@@ -0,0 +1,208 @@
+use crate::database::Entry;
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+
+/// Characters which can't appear in a tag, as they would make it ambiguous
+/// to write in frontmatter or to search for
+const INVALID_TAG_CHARS: &[char] = &[',', '#', '"', '(', ')', ':'];
+
+/// Cleans up a tag as typed by the user, eg: `#project/alpha/` becomes `project/alpha`
+///
+/// Will return an error if what's left isn't a valid tag
+pub fn normalize(tag: &str) -> Result<String> {
+    let tag = tag.trim().trim_start_matches('#').trim_matches('/');
+
+    if tag.is_empty() {
+        return Err(anyhow!("tags can't be empty"));
+    }
+
+    if tag.contains(char::is_whitespace) {
+        return Err(anyhow!("tags can't contain spaces, use `-` or `/` instead"));
+    }
+
+    if let Some(char) = tag.chars().find(|char| INVALID_TAG_CHARS.contains(char)) {
+        return Err(anyhow!("tags can't contain `{}`", char));
+    }
+
+    Ok(tag.to_string())
+}
+
+/// Tags are case insensitive, so they're compared by what this returns,
+/// eg: `Project/Ärger` and `project/ärger` are the same tag
+pub fn fold(tag: &str) -> String {
+    tag.to_lowercase()
+}
+
+/// Whether `a` and `b` are the same tag, see `fold`
+pub fn is_same(a: &str, b: &str) -> bool {
+    fold(a) == fold(b)
+}
+
+/// Whether `tags` contains `tag`, ignoring case like the rest of tag handling does
+pub fn contains(tags: &[String], tag: &str) -> bool {
+    tags.iter().any(|existing| is_same(existing, tag))
+}
+
+/// Whether `tag` is `entry_tag` or one of its parents, eg: `project` matches `project/alpha`
+pub fn is_same_or_parent(tag: &str, entry_tag: &str) -> bool {
+    let (tag, entry_tag) = (fold(tag), fold(entry_tag));
+
+    entry_tag
+        .strip_prefix(&tag)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Returns what `tag` becomes when `from` is renamed to `to`, nested tags move
+/// along with their parent, eg: renaming `project` to `work` turns
+/// `project/alpha` into `work/alpha`.
+///
+/// Returns `None` if `tag` isn't affected by the rename.
+pub fn rename(tag: &str, from: &str, to: &str) -> Option<String> {
+    if !is_same_or_parent(from, tag) {
+        return None;
+    }
+
+    // Going by the parts rather than the length of `from`, which may differ
+    // from that of the matching part of `tag` once case is ignored
+    let depth = from.split('/').count();
+
+    match tag.splitn(depth + 1, '/').nth(depth) {
+        Some(nested) => Some(format!("{}/{}", to, nested)),
+        None => Some(to.to_string()),
+    }
+}
+
+/// A tag in the hierarchy of every tag in use
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TagNode {
+    /// The full tag, eg: `project/alpha`
+    pub path: String,
+    /// The last part of the tag, eg: `alpha`
+    pub name: String,
+    /// How many levels deep the tag is nested, starting at 0
+    pub depth: usize,
+    /// How many entries have this tag or one nested under it
+    pub count: usize,
+}
+
+/// Arranges the tags of `entries` into a tree, flattened so that every tag comes
+/// right after its parent. Parents are included even if no entry uses them directly,
+/// eg: `project/alpha` on its own also yields `project`.
+pub fn tree(entries: &[Entry]) -> Vec<TagNode> {
+    // Keyed by the folded path, as tags are case insensitive
+    let mut nodes: HashMap<String, TagNode> = HashMap::new();
+
+    for entry in entries {
+        let mut seen = HashSet::new();
+
+        for tag in &entry.tags {
+            let mut end = 0;
+
+            for (depth, name) in tag.split('/').enumerate() {
+                end += name.len() + if depth == 0 { 0 } else { 1 };
+                let path = &tag[..end];
+
+                // Counted once per entry, even if several of its tags share a parent
+                if !seen.insert(fold(path)) {
+                    continue;
+                }
+
+                nodes
+                    .entry(fold(path))
+                    .or_insert_with(|| TagNode {
+                        path: path.to_string(),
+                        name: name.to_string(),
+                        depth,
+                        count: 0,
+                    })
+                    .count += 1;
+            }
+        }
+    }
+
+    let mut nodes = nodes.into_iter().collect::<Vec<(String, TagNode)>>();
+
+    // Comparing the parts of the path keeps children right below their parent,
+    // which comparing the whole string wouldn't, eg: `a-b` sorts before `a/b`
+    nodes.sort_by(|(a, _), (b, _)| a.split('/').cmp(b.split('/')));
+
+    nodes.into_iter().map(|(_, node)| node).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(tags: &[&str]) -> Entry {
+        Entry {
+            path: PathBuf::from("/notes/entry.md"),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parents_only_match_whole_parts() {
+        assert!(is_same_or_parent("project", "project"));
+        assert!(is_same_or_parent("project", "Project/alpha"));
+        assert!(is_same_or_parent("PROJECT/alpha", "project/alpha/v2"));
+        assert!(!is_same_or_parent("proj", "project"));
+        assert!(!is_same_or_parent("project/alpha", "project"));
+    }
+
+    #[test]
+    fn case_is_ignored_beyond_ascii() {
+        let tags = ["Ärger/Büro".to_string()];
+
+        assert!(contains(&tags, "ärger/büro"));
+        assert!(!contains(&tags, "ärger"));
+        assert!(is_same_or_parent("ÄRGER", "ärger/büro"));
+    }
+
+    #[test]
+    fn renames_move_nested_tags_along() {
+        assert_eq!(
+            rename("project/alpha", "project", "work").unwrap(),
+            "work/alpha"
+        );
+        assert_eq!(rename("Project", "project", "work").unwrap(), "work");
+        assert_eq!(rename("projects", "project", "work"), None);
+        // The Kelvin sign is three bytes long, but folds to a plain `k`
+        assert_eq!(
+            rename("\u{212A}alt/tee", "kalt", "cold").unwrap(),
+            "cold/tee"
+        );
+    }
+
+    #[test]
+    fn trees_keep_children_below_their_parent() {
+        let entries = [
+            entry(&["project/alpha", "project/beta"]),
+            entry(&["Project/Alpha/v2", "project-x"]),
+            entry(&["ärger"]),
+            entry(&["Ärger"]),
+        ];
+
+        let nodes = tree(&entries)
+            .into_iter()
+            .map(|node| (node.path, node.name, node.depth, node.count))
+            .collect::<Vec<(String, String, usize, usize)>>();
+
+        let node = |path: &str, name: &str, depth, count| {
+            (path.to_string(), name.to_string(), depth, count)
+        };
+
+        assert_eq!(
+            nodes,
+            [
+                node("project", "project", 0, 2),
+                node("project/alpha", "alpha", 1, 2),
+                node("Project/Alpha/v2", "v2", 2, 1),
+                node("project/beta", "beta", 1, 1),
+                node("project-x", "project-x", 0, 1),
+                node("ärger", "ärger", 0, 2),
+            ]
+        );
+    }
+}
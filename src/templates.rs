@@ -0,0 +1,252 @@
+use crate::search;
+use anyhow::{Result, anyhow};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::HashMap;
+use std::fs;
+use std::iter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Name of the templates directory, inside of the thoughts directory.
+/// Files in it aren't entries, so they are left out when polling
+pub const TEMPLATES_DIRECTORY: &str = "templates";
+
+/// A Markdown file new entries can start out as.
+///
+/// Templates may have frontmatter, eg: default tags, and may contain variables
+/// which are filled in when an entry is created from them:
+///
+/// - `{{title}}`: the title of the new entry
+/// - `{{date}}` and `{{time}}`: eg: `2026-10-17` and `09:30`, which also take a
+///   strftime-style format, eg: `{{date:%A, %B %-d}}`
+/// - `{{uuid}}`: a random UUID
+/// - `{{prompt:Question}}`: asks for a value, only once per question
+///
+/// Anything else in braces is left as it is.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Template {
+    /// File stem of the template, eg: `meeting`
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Values filled in for the variables of a template
+#[derive(Debug, Clone)]
+pub struct Variables {
+    pub title: String,
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+}
+
+impl Template {
+    /// Every template in the thoughts directory at `thoughts_path`, by name
+    pub fn list(thoughts_path: &Path) -> Result<Vec<Template>> {
+        let directory = thoughts_path.join(TEMPLATES_DIRECTORY);
+
+        if !directory.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut templates = vec![];
+
+        for file in fs::read_dir(&directory)? {
+            let path = file?.path();
+
+            if path.extension().is_none_or(|extension| extension != "md") {
+                continue;
+            }
+
+            let Some(name) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+
+            templates.push(Template { name, path });
+        }
+
+        templates.sort_by(|a, b| search::natural_cmp(&a.name, &b.name));
+
+        Ok(templates)
+    }
+
+    /// Returns the template called `name`
+    pub fn find(thoughts_path: &Path, name: &str) -> Result<Template> {
+        Template::list(thoughts_path)?
+            .into_iter()
+            .find(|template| template.name == name)
+            .ok_or(anyhow!("no template goes by `{}`", name))
+    }
+
+    /// Reads the template and fills in its variables, calling `prompt`
+    /// with the question of each `{{prompt:...}}` to get its value
+    pub fn render(
+        &self,
+        variables: &Variables,
+        prompt: &mut dyn FnMut(&str) -> Result<String>,
+    ) -> Result<String> {
+        let text = fs::read_to_string(&self.path)
+            .map_err(|err| anyhow!("unable to read template {}: {}", self.path.display(), err))?;
+
+        render(&text, variables, prompt)
+    }
+
+    /// Whether the template has a `{{title}}` to fill in
+    pub fn uses_title(&self) -> Result<bool> {
+        let text = fs::read_to_string(&self.path)?;
+
+        Ok(
+            variables(&text)
+                .any(|variable| variable.name == "title" && variable.argument.is_none()),
+        )
+    }
+}
+
+/// Fills in the variables of `text`, see `Template`
+pub fn render(
+    text: &str,
+    variables: &Variables,
+    prompt: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let mut output = String::new();
+    let mut answers: HashMap<String, String> = HashMap::new();
+    let mut end = 0;
+
+    for variable in self::variables(text) {
+        output.push_str(&text[end..variable.range.start]);
+        end = variable.range.end;
+
+        let value = match (variable.name, variable.argument) {
+            ("title", None) => Some(variables.title.clone()),
+            ("date", format) => formatted(format.unwrap_or("%Y-%m-%d"), |items| {
+                variables.date.format_with_items(items).to_string()
+            }),
+            ("time", format) => formatted(format.unwrap_or("%H:%M"), |items| {
+                variables.time.format_with_items(items).to_string()
+            }),
+            ("uuid", None) => Some(uuid::Uuid::new_v4().to_string()),
+            ("prompt", Some(question)) => {
+                if !answers.contains_key(question) {
+                    answers.insert(question.to_string(), prompt(question)?);
+                }
+
+                answers.get(question).cloned()
+            }
+            _ => None,
+        };
+
+        match value {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&text[variable.range]),
+        }
+    }
+
+    output.push_str(&text[end..]);
+
+    Ok(output)
+}
+
+/// Something in braces, eg: `{{ date:%A }}`
+struct Variable<'a> {
+    /// Byte range of the variable, braces included
+    range: Range<usize>,
+    /// Trimmed name, eg: `date`
+    name: &'a str,
+    /// Whatever comes after the first `:`, trimmed, eg: `%A`
+    argument: Option<&'a str>,
+}
+
+/// Every variable in `text`, known or not, in order
+fn variables(text: &str) -> impl Iterator<Item = Variable<'_>> {
+    let mut offset = 0;
+
+    iter::from_fn(move || {
+        let start = offset + text[offset..].find("{{")?;
+        let end = start + text[start..].find("}}")? + 2;
+        offset = end;
+
+        let inner = &text[start + 2..end - 2];
+        let (name, argument) = match inner.split_once(':') {
+            Some((name, argument)) => (name.trim(), Some(argument.trim())),
+            None => (inner.trim(), None),
+        };
+
+        Some(Variable {
+            range: start..end,
+            name,
+            argument,
+        })
+    })
+}
+
+/// Formats with `format` if it's a valid strftime-style format, as formatting
+/// with an invalid one would panic
+fn formatted(format: &str, format_with: impl FnOnce(StrftimeItems) -> String) -> Option<String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return None;
+    }
+
+    Some(format_with(StrftimeItems::new(format)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        Variables {
+            title: "Standup".to_string(),
+            date: NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(),
+            time: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+        }
+    }
+
+    fn render(text: &str, prompt: &mut dyn FnMut(&str) -> Result<String>) -> String {
+        super::render(text, &variables(), prompt).unwrap()
+    }
+
+    #[test]
+    fn fills_in_variables_with_or_without_spaces() {
+        let text = "# {{title}} / {{ title }}\n{{date}} {{ date:%A }} {{time}}";
+
+        assert_eq!(
+            render(text, &mut |_| unreachable!()),
+            "# Standup / Standup\n2026-10-17 Saturday 09:30"
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_and_invalid_variables_alone() {
+        let text = "{{unknown}} {{date:%Q}} {{title:x}} {{unclosed";
+
+        assert_eq!(render(text, &mut |_| unreachable!()), text);
+    }
+
+    #[test]
+    fn asks_each_question_once() {
+        let mut questions = vec![];
+        let text = "{{prompt:Who}} and {{ prompt: Who }} on {{prompt:What}}";
+
+        let output = render(text, &mut |question| {
+            questions.push(question.to_string());
+            Ok(question.to_lowercase())
+        });
+
+        assert_eq!(output, "who and who on what");
+        assert_eq!(questions, ["Who", "What"]);
+    }
+
+    #[test]
+    fn finds_the_title_however_it_is_spaced() {
+        let names = |text| {
+            super::variables(text)
+                .map(|variable| variable.name)
+                .collect::<Vec<&str>>()
+        };
+
+        assert_eq!(names("{{ title }}, {{date:%H}}"), ["title", "date"]);
+        assert!(names("{{title").is_empty());
+    }
+}
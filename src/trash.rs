@@ -0,0 +1,367 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Name of the trash directory, inside of the thoughts directory
+pub const TRASH_DIRECTORY: &str = ".trash";
+
+/// Deleted entries, kept around until they are restored or purged.
+///
+/// Every entry is moved into the trash directory as `<id>.md`, next to an
+/// `<id>.toml` sidecar recording where it came from and when it was deleted.
+#[derive(Debug, Default, Clone)]
+pub struct Trash {
+    path: PathBuf,
+    /// The thoughts directory, which entries are restored into
+    thoughts_path: PathBuf,
+}
+
+/// An entry sitting in the trash
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TrashedEntry {
+    /// Identifies the entry within the trash, eg: `1718000000-standup`
+    #[serde(skip)]
+    pub id: String,
+    /// Where the entry was before it was deleted, relative to the thoughts
+    /// directory, so the trash still works once it's synced to another machine
+    pub original_path: PathBuf,
+    /// Seconds since the Unix epoch
+    pub deleted_at: u64,
+}
+
+impl TrashedEntry {
+    /// The title the entry had before it was deleted
+    pub fn title(&self) -> String {
+        self.original_path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string())
+    }
+}
+
+impl Trash {
+    /// The trash of the thoughts directory at `thoughts_path`,
+    /// the trash directory itself is only created once something is deleted
+    pub fn new(thoughts_path: &Path) -> Trash {
+        Trash {
+            path: thoughts_path.join(TRASH_DIRECTORY),
+            thoughts_path: thoughts_path.to_path_buf(),
+        }
+    }
+
+    /// Path of the trash directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Moves the file at `path` into the trash
+    pub fn put(&self, path: &Path) -> Result<TrashedEntry> {
+        let original_path = path
+            .strip_prefix(&self.thoughts_path)
+            .map_err(|_| anyhow!("{} isn't in the thoughts directory", path.display()))?
+            .to_path_buf();
+
+        fs::create_dir_all(&self.path)?;
+
+        let deleted_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let stem = path
+            .file_stem()
+            .ok_or(anyhow!("unable to parse entry name"))?
+            .to_string_lossy();
+
+        // Deleting the same title twice in a second is unlikely, but not impossible
+        let mut id = format!("{}-{}", deleted_at, stem);
+        let mut counter = 2;
+
+        while self.file_path(&id).exists() || self.sidecar_path(&id).exists() {
+            id = format!("{}-{} {}", deleted_at, stem, counter);
+            counter += 1;
+        }
+
+        let entry = TrashedEntry {
+            id,
+            original_path,
+            deleted_at,
+        };
+
+        // The sidecar goes first, so a file in the trash always knows where it came from
+        fs::write(self.sidecar_path(&entry.id), toml::to_string(&entry)?)?;
+
+        if let Err(err) = fs::rename(path, self.file_path(&entry.id)) {
+            let _ = fs::remove_file(self.sidecar_path(&entry.id));
+            return Err(err.into());
+        }
+
+        Ok(entry)
+    }
+
+    /// Every entry in the trash, most recently deleted first
+    pub fn list(&self) -> Result<Vec<TrashedEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut entries = vec![];
+
+        for file in fs::read_dir(&self.path)? {
+            let path = file?.path();
+
+            if path.extension().is_none_or(|extension| extension != "toml") {
+                continue;
+            }
+
+            let Some(id) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+
+            // Sidecars which lost their file are of no use to anyone
+            if !self.file_path(&id).exists() {
+                continue;
+            }
+
+            let Ok(mut entry) = toml::from_str::<TrashedEntry>(&fs::read_to_string(&path)?) else {
+                continue;
+            };
+
+            // Older sidecars stored the absolute path
+            if let Ok(relative_path) = entry.original_path.strip_prefix(&self.thoughts_path) {
+                entry.original_path = relative_path.to_path_buf();
+            }
+
+            entry.id = id;
+            entries.push(entry);
+        }
+
+        entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then(a.id.cmp(&b.id)));
+
+        Ok(entries)
+    }
+
+    /// Returns the entry with `id`, which may also be given as just its title
+    /// as long as only one entry in the trash had that title
+    pub fn find(&self, id: &str) -> Result<TrashedEntry> {
+        let entries = self.list()?;
+
+        if let Some(entry) = entries.iter().find(|entry| entry.id == id) {
+            return Ok(entry.clone());
+        }
+
+        let mut matches = entries.into_iter().filter(|entry| entry.title() == id);
+
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (Some(_), Some(_)) => Err(anyhow!(
+                "more than one entry titled `{}` is in the trash, use its id instead",
+                id
+            )),
+            (None, _) => Err(anyhow!("nothing in the trash goes by `{}`", id)),
+        }
+    }
+
+    /// Moves `entry` back to where it was deleted from, and returns its path.
+    ///
+    /// Will return an error rather than overwrite a file which took its place
+    pub fn restore(&self, entry: &TrashedEntry) -> Result<PathBuf> {
+        // Sidecars may have been written on another machine, or edited by hand,
+        // so make sure nothing ends up outside of the thoughts directory
+        let is_inside = entry
+            .original_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+        if !is_inside || entry.original_path.as_os_str().is_empty() {
+            return Err(anyhow!(
+                "{} is outside of the thoughts directory, refusing to restore it there",
+                entry.original_path.display()
+            ));
+        }
+
+        let path = self.thoughts_path.join(&entry.original_path);
+
+        if path.exists() {
+            return Err(anyhow!(
+                "{} already exists, rename it first",
+                path.display()
+            ));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::rename(self.file_path(&entry.id), &path)?;
+        fs::remove_file(self.sidecar_path(&entry.id))?;
+
+        Ok(path)
+    }
+
+    /// Deletes `entry` for good
+    pub fn purge(&self, entry: &TrashedEntry) -> Result<()> {
+        fs::remove_file(self.file_path(&entry.id))?;
+        fs::remove_file(self.sidecar_path(&entry.id))?;
+
+        Ok(())
+    }
+
+    /// Deletes every entry which has been in the trash for longer than `retention`.
+    /// Returns how many entries were purged.
+    pub fn purge_older_than(&self, retention: Duration) -> Result<usize> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut purged = 0;
+
+        for entry in self.list()? {
+            if now.saturating_sub(entry.deleted_at) > retention.as_secs() {
+                self.purge(&entry)?;
+                purged += 1;
+            }
+        }
+
+        Ok(purged)
+    }
+
+    fn file_path(&self, id: &str) -> PathBuf {
+        self.path.join(format!("{}.md", id))
+    }
+
+    fn sidecar_path(&self, id: &str) -> PathBuf {
+        self.path.join(format!("{}.toml", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Trash) {
+        let directory = TempDir::new().unwrap();
+        let trash = Trash::new(directory.path());
+
+        (directory, trash)
+    }
+
+    fn write(directory: &TempDir, name: &str, text: &str) -> PathBuf {
+        let path = directory.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn round_trips_through_the_trash() {
+        let (directory, trash) = setup();
+        let path = write(&directory, "work/standup.md", "notes");
+
+        let entry = trash.put(&path).unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(entry.original_path, Path::new("work/standup.md"));
+        assert_eq!(entry.title(), "standup");
+        assert_eq!(trash.list().unwrap().len(), 1);
+        assert_eq!(trash.find("standup").unwrap(), entry);
+
+        // The directory it was in doesn't have to be there anymore
+        fs::remove_dir(directory.path().join("work")).unwrap();
+
+        assert_eq!(trash.restore(&entry).unwrap(), path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "notes");
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn sidecars_store_paths_relative_to_the_thoughts_directory() {
+        let (directory, trash) = setup();
+        let entry = trash.put(&write(&directory, "standup.md", "")).unwrap();
+
+        let sidecar = fs::read_to_string(trash.sidecar_path(&entry.id)).unwrap();
+
+        assert!(sidecar.contains("original_path = \"standup.md\""));
+    }
+
+    #[test]
+    fn refuses_to_overwrite_on_restore() {
+        let (directory, trash) = setup();
+        let path = write(&directory, "standup.md", "old");
+
+        let entry = trash.put(&path).unwrap();
+        write(&directory, "standup.md", "new");
+
+        assert!(trash.restore(&entry).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(trash.list().unwrap(), [entry]);
+    }
+
+    #[test]
+    fn refuses_to_restore_outside_of_the_thoughts_directory() {
+        let (directory, trash) = setup();
+
+        for original_path in ["../escaped.md", "/tmp/escaped.md", "a/../../escaped.md", ""] {
+            let entry = trash.put(&write(&directory, "standup.md", "")).unwrap();
+            fs::write(
+                trash.sidecar_path(&entry.id),
+                format!("original_path = {:?}\ndeleted_at = 0\n", original_path),
+            )
+            .unwrap();
+
+            let entry = trash.find(&entry.id).unwrap();
+
+            assert!(trash.restore(&entry).is_err(), "{}", original_path);
+            assert!(trash.file_path(&entry.id).exists());
+        }
+    }
+
+    #[test]
+    fn reads_absolute_paths_of_older_sidecars() {
+        let (directory, trash) = setup();
+        let path = write(&directory, "standup.md", "");
+        let entry = trash.put(&path).unwrap();
+
+        fs::write(
+            trash.sidecar_path(&entry.id),
+            format!("original_path = {:?}\ndeleted_at = 0\n", path),
+        )
+        .unwrap();
+
+        let entry = trash.find(&entry.id).unwrap();
+
+        assert_eq!(entry.original_path, Path::new("standup.md"));
+        assert_eq!(trash.restore(&entry).unwrap(), path);
+    }
+
+    #[test]
+    fn purges_entries_past_their_retention() {
+        let (directory, trash) = setup();
+        let old = trash.put(&write(&directory, "old.md", "")).unwrap();
+        let new = trash.put(&write(&directory, "new.md", "")).unwrap();
+
+        let mut sidecar = old.clone();
+        sidecar.deleted_at = 0;
+        fs::write(
+            trash.sidecar_path(&old.id),
+            toml::to_string(&sidecar).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(trash.purge_older_than(Duration::from_secs(60)).unwrap(), 1);
+        assert_eq!(trash.list().unwrap(), [new]);
+        assert!(!trash.file_path(&old.id).exists());
+        assert!(!trash.sidecar_path(&old.id).exists());
+    }
+
+    #[test]
+    fn same_title_twice_gets_two_ids() {
+        let (directory, trash) = setup();
+
+        let first = trash.put(&write(&directory, "standup.md", "1")).unwrap();
+        let second = trash.put(&write(&directory, "standup.md", "2")).unwrap();
+
+        assert_ne!(first.id, second.id);
+        assert!(trash.find("standup").is_err());
+        assert_eq!(trash.find(&second.id).unwrap(), second);
+    }
+}
@@ -0,0 +1,77 @@
+use notify::event::EventKind;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::time;
+
+/// How long things have to stay quiet before a burst of events is reported,
+/// editors and sync tools tend to touch a file several times in a row
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// How often to check for changes when the filesystem can't be watched
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Reports when something changed inside of the thoughts directory.
+///
+/// Uses the native mechanism of the OS (inotify on Linux) when possible, and
+/// otherwise falls back to reporting a possible change on a fixed interval,
+/// which is cheap since `Database::poll` only re-reads changed files.
+pub struct Watcher {
+    /// Kept around since dropping it stops the watch
+    _watcher: Option<RecommendedWatcher>,
+    events: Option<UnboundedReceiver<()>>,
+}
+
+impl Watcher {
+    /// Starts watching `path` recursively.
+    /// When `native` is false, or watching fails, changes are polled for instead.
+    pub fn new(path: &Path, native: bool) -> Watcher {
+        let polling = Watcher {
+            _watcher: None,
+            events: None,
+        };
+
+        if !native {
+            return polling;
+        }
+
+        let (sender, events) = mpsc::unbounded_channel();
+
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // Reads don't change anything, and would otherwise fire every time we poll
+            if let Ok(event) = event
+                && !matches!(event.kind, EventKind::Access(_))
+            {
+                let _ = sender.send(());
+            }
+        });
+
+        match watcher {
+            Ok(mut watcher) => match watcher.watch(path, RecursiveMode::Recursive) {
+                Ok(()) => Watcher {
+                    _watcher: Some(watcher),
+                    events: Some(events),
+                },
+                Err(_) => polling,
+            },
+            Err(_) => polling,
+        }
+    }
+
+    /// Waits until something may have changed, and the events have settled down
+    pub async fn changed(&mut self) {
+        let Some(events) = &mut self.events else {
+            time::sleep(POLL_INTERVAL).await;
+            return;
+        };
+
+        if events.recv().await.is_none() {
+            // The watcher died, so there is nothing left to do but poll
+            self.events = None;
+            return;
+        }
+
+        while let Ok(Some(())) = time::timeout(DEBOUNCE, events.recv()).await {}
+    }
+}